@@ -1,14 +1,18 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
 use std::time::Duration;
 
 use actix_rt::ArbiterHandle;
 use futures_core::stream::Stream;
 use log::error;
+use tokio::sync::oneshot;
 
 use crate::address::{channel, Addr};
 use crate::context::Context;
 use crate::contextitems::{ActorDelayedMessageItem, ActorMessageItem, ActorMessageStreamItem};
 use crate::fut::{ActorFuture, ActorStreamExt};
-use crate::handler::{Handler, Message};
+use crate::handler::{Handler, Message, MessageResponse};
 use crate::mailbox::DEFAULT_CAPACITY;
 use crate::stream::StreamHandler;
 use crate::utils::{IntervalFunc, TimerFunc};
@@ -191,6 +195,39 @@ pub trait Actor: Sized + Unpin + 'static {
         let act = f(&mut ctx);
         ctx.run(act)
     }
+
+    /// Start a new asynchronous actor, returning its address and a handle
+    /// that resolves with the actor's [`ExitReason`] once it has stopped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use actix::*;
+    ///
+    /// struct MyActor;
+    /// impl Actor for MyActor {
+    ///     type Context = Context<Self>;
+    /// }
+    ///
+    /// fn main() {
+    ///     System::new().block_on(async {
+    ///         let (addr, handle) = MyActor.start_with_handle();
+    ///         drop(addr); // <- the actor stops once all addresses are dropped
+    ///         assert_eq!(handle.await, ExitReason::Normal);
+    ///     });
+    /// }
+    /// ```
+    fn start_with_handle(self) -> (Addr<Self>, ActorHandle)
+    where
+        Self: Actor<Context = Context<Self>>,
+    {
+        let ctx = Context::new();
+        let (tx, rx) = oneshot::channel();
+        ctx.address().link().on_exit(Box::new(move |reason| {
+            let _ = tx.send(reason.clone());
+        }));
+        (ctx.run(self), ActorHandle { rx })
+    }
 }
 
 #[allow(unused_variables)]
@@ -225,6 +262,42 @@ pub enum ActorState {
     Stopped,
 }
 
+/// The reason an actor stopped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExitReason {
+    /// All addresses were dropped and the actor had nothing left to do.
+    Normal,
+    /// The actor was stopped with [`ActorContext::stop`], or by its parent.
+    Stopped,
+    /// The actor was terminated with [`ActorContext::terminate`].
+    Terminated,
+    /// The actor panicked while its panics were being caught.
+    Panicked,
+    /// The actor was stopped with
+    /// [`Context::stop_with_reason`](crate::Context::stop_with_reason).
+    Custom(String),
+}
+
+/// A handle to a running actor, returned by [`Actor::start_with_handle`].
+///
+/// The handle is a future that resolves with the [`ExitReason`] once the
+/// actor has stopped. Dropping the handle does not affect the actor.
+#[derive(Debug)]
+pub struct ActorHandle {
+    rx: oneshot::Receiver<ExitReason>,
+}
+
+impl Future for ActorHandle {
+    type Output = ExitReason;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        // the sender only goes away unused if the exit hooks never ran
+        Pin::new(&mut self.rx)
+            .poll(cx)
+            .map(|res| res.unwrap_or(ExitReason::Terminated))
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Running {
     Stop,
@@ -403,6 +476,18 @@ where
         }
     }
 
+    /// Handles a message delivered by the context itself, such as
+    /// [`notify`](AsyncContext::notify) messages and message streams.
+    #[doc(hidden)]
+    fn handle_message<M>(&mut self, act: &mut A, msg: M)
+    where
+        A: Handler<M>,
+        M: Message + 'static,
+    {
+        let fut = A::handle(act, msg, self);
+        fut.handle(self, None)
+    }
+
     /// Sends the message `msg` to self. This bypasses the mailbox capacity, and
     /// will always queue the message. If the actor is in the `stopped` state, an
     /// error will be raised.
@@ -1,3 +1,4 @@
 //! Helper actors
 
 pub mod mocker;
+pub mod router;
@@ -0,0 +1,534 @@
+//! Router actors.
+//!
+//! A router spreads the messages it receives over a pool of routees, picking
+//! one routee per message according to its [`Strategy`]. [`Router`] routes to
+//! addresses of a single actor type, while [`RecipientRouter`] routes a single
+//! message type to any actors that handle it.
+//!
+//! Routers are regular actors: messages are sent to their `Addr`, and
+//! routees can be added and removed at runtime with the [`AddRoutee`] and
+//! [`RemoveRoutee`] messages. Routees that have stopped are removed from the
+//! pool once the router notices. [`Broadcast`] sends a message to every
+//! routee regardless of the strategy.
+//!
+//! Messages need to implement [`RouteKey`] to be routed. The key is only used
+//! by the [`Strategy::ConsistentHash`] strategy, so the default implementation
+//! is enough for other strategies.
+//!
+//! ```
+//! use actix::actors::router::{RouteKey, Router, Strategy};
+//! use actix::prelude::*;
+//!
+//! struct Work(u64);
+//!
+//! impl Message for Work {
+//!     type Result = u64;
+//! }
+//!
+//! impl RouteKey for Work {}
+//!
+//! struct Worker;
+//!
+//! impl Actor for Worker {
+//!     type Context = Context<Self>;
+//! }
+//!
+//! impl Handler<Work> for Worker {
+//!     type Result = u64;
+//!
+//!     fn handle(&mut self, msg: Work, _: &mut Self::Context) -> u64 {
+//!         msg.0 * 2
+//!     }
+//! }
+//!
+//! # fn main() {
+//! # System::new().block_on(async {
+//! let router = Router::new(Strategy::RoundRobin)
+//!     .routees((0..4).map(|_| Worker.start()))
+//!     .start();
+//!
+//! assert_eq!(router.send(Work(21)).await.unwrap(), 42);
+//! # });
+//! # }
+//! ```
+
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use crate::address::{Addr, MailboxError, Recipient, ToEnvelope};
+use crate::handler::{Handler, Message, MessageResponse, OneshotSender, ResponseFuture};
+use crate::{Actor, Context};
+
+/// Number of points each routee occupies on the consistent hash ring.
+const VIRTUAL_NODES: usize = 32;
+
+/// How a router picks the routee for a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Routees take turns.
+    RoundRobin,
+    /// A random routee is picked for every message.
+    Random,
+    /// Messages with the same [`RouteKey`] go to the same routee. Adding or
+    /// removing a routee only moves the keys of a fraction of the routees.
+    /// Messages without a key are routed round robin.
+    ConsistentHash,
+    /// The routee with the fewest queued messages is picked.
+    SmallestMailbox,
+}
+
+/// A message that can be routed.
+///
+/// ```
+/// # use actix::prelude::*;
+/// use actix::actors::router::RouteKey;
+///
+/// struct Session {
+///     user_id: u64,
+/// }
+///
+/// impl Message for Session {
+///     type Result = ();
+/// }
+///
+/// // sessions of the same user are handled by the same routee
+/// impl RouteKey for Session {
+///     fn route_key(&self) -> Option<u64> {
+///         Some(self.user_id)
+///     }
+/// }
+/// ```
+pub trait RouteKey {
+    /// Returns the key used by [`Strategy::ConsistentHash`].
+    fn route_key(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Sends a message to every routee of a router.
+///
+/// Resolves with the results of all routees, in the order of the routees.
+pub struct Broadcast<M>(pub M);
+
+impl<M: Message> Message for Broadcast<M> {
+    type Result = Vec<Result<M::Result, MailboxError>>;
+}
+
+/// Adds a routee to a router.
+pub struct AddRoutee<R>(pub R);
+
+impl<R> Message for AddRoutee<R> {
+    type Result = ();
+}
+
+/// Removes a routee from a router.
+///
+/// Resolves with `false` if the routee was not part of the pool.
+pub struct RemoveRoutee<R>(pub R);
+
+impl<R> Message for RemoveRoutee<R> {
+    type Result = bool;
+}
+
+/// The response of a router to a routed message.
+///
+/// Resolves the request with the response of the routee the message was
+/// routed to. Fails with [`MailboxError::Closed`] if the router has no
+/// routees.
+pub struct Routed<M: Message> {
+    fut: Option<ResponseFuture<Result<M::Result, MailboxError>>>,
+}
+
+impl<M: Message> Routed<M> {
+    fn new<F>(fut: F) -> Self
+    where
+        F: Future<Output = Result<M::Result, MailboxError>> + 'static,
+    {
+        Routed {
+            fut: Some(Box::pin(fut)),
+        }
+    }
+
+    fn closed() -> Self {
+        Routed { fut: None }
+    }
+}
+
+impl<A: Actor, M: Message> MessageResponse<A, M> for Routed<M> {
+    fn handle(self, _: &mut A::Context, tx: Option<OneshotSender<M::Result>>) {
+        let fut = match self.fut {
+            Some(fut) => fut,
+            None => {
+                if let Some(tx) = tx {
+                    tx.fail(MailboxError::Closed);
+                }
+                return;
+            }
+        };
+
+        actix_rt::spawn(async move {
+            let res = fut.await;
+            if let Some(tx) = tx {
+                match res {
+                    Ok(res) => {
+                        let _ = tx.send(res);
+                    }
+                    Err(err) => tx.fail(err),
+                }
+            }
+        });
+    }
+}
+
+/// Routes messages over a pool of actors of type `A`.
+pub struct Router<A: Actor> {
+    pool: Pool<Addr<A>>,
+}
+
+impl<A: Actor> Router<A> {
+    /// Creates a router without routees.
+    pub fn new(strategy: Strategy) -> Self {
+        Router {
+            pool: Pool::new(strategy),
+        }
+    }
+
+    /// Adds routees to the router.
+    pub fn routees<I>(mut self, routees: I) -> Self
+    where
+        I: IntoIterator<Item = Addr<A>>,
+    {
+        for addr in routees {
+            self.pool.add(addr);
+        }
+        self
+    }
+}
+
+impl<A: Actor> Actor for Router<A> {
+    type Context = Context<Self>;
+}
+
+impl<A, M> Handler<M> for Router<A>
+where
+    A: Actor + Handler<M>,
+    A::Context: ToEnvelope<A, M>,
+    M: Message + RouteKey + Send + 'static,
+    M::Result: Send,
+{
+    type Result = Routed<M>;
+
+    fn handle(&mut self, msg: M, _: &mut Self::Context) -> Routed<M> {
+        let key = msg.route_key();
+        match self.pool.select(key) {
+            Some(addr) => Routed::new(addr.send(msg)),
+            None => Routed::closed(),
+        }
+    }
+}
+
+impl<A, M> Handler<Broadcast<M>> for Router<A>
+where
+    A: Actor + Handler<M>,
+    A::Context: ToEnvelope<A, M>,
+    M: Message + Clone + Send + 'static,
+    M::Result: Send,
+{
+    type Result = ResponseFuture<Vec<Result<M::Result, MailboxError>>>;
+
+    fn handle(&mut self, msg: Broadcast<M>, _: &mut Self::Context) -> Self::Result {
+        self.pool.prune();
+        let reqs: Vec<_> = self
+            .pool
+            .routees
+            .iter()
+            .map(|addr| addr.send(msg.0.clone()))
+            .collect();
+        Box::pin(collect(reqs))
+    }
+}
+
+impl<A: Actor> Handler<AddRoutee<Addr<A>>> for Router<A> {
+    type Result = ();
+
+    fn handle(&mut self, msg: AddRoutee<Addr<A>>, _: &mut Self::Context) {
+        self.pool.add(msg.0);
+    }
+}
+
+impl<A: Actor> Handler<RemoveRoutee<Addr<A>>> for Router<A> {
+    type Result = bool;
+
+    fn handle(&mut self, msg: RemoveRoutee<Addr<A>>, _: &mut Self::Context) -> bool {
+        self.pool.remove(&msg.0)
+    }
+}
+
+/// Routes messages of type `M` over a pool of recipients.
+pub struct RecipientRouter<M>
+where
+    M: Message + Send,
+    M::Result: Send,
+{
+    pool: Pool<Recipient<M>>,
+}
+
+impl<M> RecipientRouter<M>
+where
+    M: Message + Send,
+    M::Result: Send,
+{
+    /// Creates a router without routees.
+    pub fn new(strategy: Strategy) -> Self {
+        RecipientRouter {
+            pool: Pool::new(strategy),
+        }
+    }
+
+    /// Adds routees to the router.
+    pub fn routees<I>(mut self, routees: I) -> Self
+    where
+        I: IntoIterator<Item = Recipient<M>>,
+    {
+        for recipient in routees {
+            self.pool.add(recipient);
+        }
+        self
+    }
+}
+
+impl<M> Actor for RecipientRouter<M>
+where
+    M: Message + Send + 'static,
+    M::Result: Send,
+{
+    type Context = Context<Self>;
+}
+
+impl<M> Handler<M> for RecipientRouter<M>
+where
+    M: Message + RouteKey + Send + 'static,
+    M::Result: Send,
+{
+    type Result = Routed<M>;
+
+    fn handle(&mut self, msg: M, _: &mut Self::Context) -> Routed<M> {
+        let key = msg.route_key();
+        match self.pool.select(key) {
+            Some(recipient) => Routed::new(recipient.send(msg)),
+            None => Routed::closed(),
+        }
+    }
+}
+
+impl<M> Handler<Broadcast<M>> for RecipientRouter<M>
+where
+    M: Message + Clone + Send + 'static,
+    M::Result: Send,
+{
+    type Result = ResponseFuture<Vec<Result<M::Result, MailboxError>>>;
+
+    fn handle(&mut self, msg: Broadcast<M>, _: &mut Self::Context) -> Self::Result {
+        self.pool.prune();
+        let reqs: Vec<_> = self
+            .pool
+            .routees
+            .iter()
+            .map(|recipient| recipient.send(msg.0.clone()))
+            .collect();
+        Box::pin(collect(reqs))
+    }
+}
+
+impl<M> Handler<AddRoutee<Recipient<M>>> for RecipientRouter<M>
+where
+    M: Message + Send + 'static,
+    M::Result: Send,
+{
+    type Result = ();
+
+    fn handle(&mut self, msg: AddRoutee<Recipient<M>>, _: &mut Self::Context) {
+        self.pool.add(msg.0);
+    }
+}
+
+impl<M> Handler<RemoveRoutee<Recipient<M>>> for RecipientRouter<M>
+where
+    M: Message + Send + 'static,
+    M::Result: Send,
+{
+    type Result = bool;
+
+    fn handle(&mut self, msg: RemoveRoutee<Recipient<M>>, _: &mut Self::Context) -> bool {
+        self.pool.remove(&msg.0)
+    }
+}
+
+async fn collect<F, R>(reqs: Vec<F>) -> Vec<Result<R, MailboxError>>
+where
+    F: Future<Output = Result<R, MailboxError>>,
+{
+    // all messages are already queued, awaiting them in turn does not delay
+    // any of the routees
+    let mut res = Vec::with_capacity(reqs.len());
+    for req in reqs {
+        res.push(req.await);
+    }
+    res
+}
+
+/// An address a router can route to.
+trait Routee: Hash + Eq {
+    fn connected(&self) -> bool;
+
+    fn queued(&self) -> usize;
+}
+
+impl<A: Actor> Routee for Addr<A> {
+    fn connected(&self) -> bool {
+        Addr::connected(self)
+    }
+
+    fn queued(&self) -> usize {
+        Addr::queued(self)
+    }
+}
+
+impl<M> Routee for Recipient<M>
+where
+    M: Message + Send,
+    M::Result: Send,
+{
+    fn connected(&self) -> bool {
+        Recipient::connected(self)
+    }
+
+    fn queued(&self) -> usize {
+        Recipient::queued(self)
+    }
+}
+
+/// The routees of a router and the state of its strategy.
+struct Pool<R> {
+    strategy: Strategy,
+    routees: Vec<R>,
+    /// Consistent hash ring, mapping points to indices into `routees`.
+    ring: BTreeMap<u64, usize>,
+    next: usize,
+    seed: u64,
+}
+
+impl<R: Routee> Pool<R> {
+    fn new(strategy: Strategy) -> Self {
+        Pool {
+            strategy,
+            routees: Vec::new(),
+            ring: BTreeMap::new(),
+            next: 0,
+            seed: RandomState::new().build_hasher().finish() | 1,
+        }
+    }
+
+    fn add(&mut self, routee: R) {
+        if !self.routees.contains(&routee) {
+            self.routees.push(routee);
+            self.rebuild_ring();
+        }
+    }
+
+    fn remove(&mut self, routee: &R) -> bool {
+        let len = self.routees.len();
+        self.routees.retain(|r| r != routee);
+        if self.routees.len() == len {
+            return false;
+        }
+        self.rebuild_ring();
+        true
+    }
+
+    /// Removes routees that have stopped.
+    fn prune(&mut self) {
+        let len = self.routees.len();
+        self.routees.retain(Routee::connected);
+        if self.routees.len() != len {
+            self.rebuild_ring();
+        }
+    }
+
+    /// Picks the routee for a message with the given key.
+    fn select(&mut self, key: Option<u64>) -> Option<&R> {
+        loop {
+            if self.routees.is_empty() {
+                return None;
+            }
+
+            let idx = match (self.strategy, key) {
+                (Strategy::Random, _) => (self.random() % self.routees.len() as u64) as usize,
+                (Strategy::ConsistentHash, Some(key)) => {
+                    let point = hash(&key);
+                    let (_, idx) = self
+                        .ring
+                        .range(point..)
+                        .next()
+                        .or_else(|| self.ring.iter().next())
+                        .unwrap();
+                    *idx
+                }
+                (Strategy::SmallestMailbox, _) => {
+                    // start at a different routee each time, so that idle
+                    // routees take turns
+                    let len = self.routees.len();
+                    let start = self.next_index();
+                    (start..start + len)
+                        .map(|idx| idx % len)
+                        .min_by_key(|idx| self.routees[*idx].queued())
+                        .unwrap()
+                }
+                (Strategy::RoundRobin, _) | (Strategy::ConsistentHash, None) => {
+                    self.next_index()
+                }
+            };
+
+            if self.routees[idx].connected() {
+                return Some(&self.routees[idx]);
+            }
+            self.prune();
+        }
+    }
+
+    fn next_index(&mut self) -> usize {
+        let idx = self.next % self.routees.len();
+        self.next = idx + 1;
+        idx
+    }
+
+    /// xorshift64
+    fn random(&mut self) -> u64 {
+        let mut x = self.seed;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.seed = x;
+        x
+    }
+
+    fn rebuild_ring(&mut self) {
+        if self.strategy != Strategy::ConsistentHash {
+            return;
+        }
+        self.ring.clear();
+        for (idx, routee) in self.routees.iter().enumerate() {
+            for node in 0..VIRTUAL_NODES {
+                self.ring.insert(hash(&(routee, node)), idx);
+            }
+        }
+    }
+}
+
+fn hash<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
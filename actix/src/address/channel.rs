@@ -3,6 +3,7 @@
 use std::{
     fmt,
     hash::{Hash, Hasher},
+    mem,
     pin::Pin,
     sync::{
         atomic::{
@@ -17,14 +18,14 @@ use std::{
 
 use futures_core::{stream::Stream, task::__internal::AtomicWaker};
 use parking_lot::Mutex;
-use tokio::sync::oneshot::{channel as oneshot_channel, Receiver as OneshotReceiver};
 
-use crate::actor::Actor;
-use crate::handler::{Handler, Message};
+use crate::actor::{Actor, ExitReason};
+use crate::handler::{oneshot_channel, Handler, Message, OneshotReceiver, Priority};
+use crate::mailbox::{MailboxPolicy, MailboxStats};
 
-use super::envelope::{Envelope, ToEnvelope};
+use super::envelope::{Envelope, EnvelopeProxy, ToEnvelope};
 use super::queue::Queue;
-use super::SendError;
+use super::{ActorId, MailboxError, SendError};
 
 pub trait Sender<M>: Send
 where
@@ -42,6 +43,8 @@ where
     fn hash(&self) -> usize;
 
     fn connected(&self) -> bool;
+
+    fn queued(&self) -> usize;
 }
 
 impl<S, M> Sender<M> for Box<S>
@@ -73,6 +76,10 @@ where
     fn connected(&self) -> bool {
         (**self).connected()
     }
+
+    fn queued(&self) -> usize {
+        (**self).queued()
+    }
 }
 
 pub(crate) trait WeakSender<M>: Send
@@ -136,6 +143,25 @@ impl<A: Actor> fmt::Debug for WeakAddressSender<A> {
 
 trait AssertKinds: Send + Sync + Clone {}
 
+/// Callback invoked once the actor at the receiving end of a channel exits.
+pub(crate) type ExitHook = Box<dyn FnOnce(&ExitReason) + Send>;
+
+/// Type erased view of an actor's channel, used to link actors together.
+pub(crate) trait ActorLink: Send + Sync {
+    /// Returns the id of the actor.
+    fn id(&self) -> ActorId;
+
+    /// Asks the actor to stop.
+    fn stop(&self);
+
+    /// Registers a hook to run once the actor exits. The hook runs
+    /// immediately if the actor has already exited.
+    fn on_exit(&self, hook: ExitHook);
+
+    /// Returns whether the actor has exited.
+    fn exited(&self) -> bool;
+}
+
 /// The receiving end of a channel which implements the `Stream` trait.
 ///
 /// This is a concrete implementation of a stream which can be used to represent
@@ -143,6 +169,9 @@ trait AssertKinds: Send + Sync + Clone {}
 /// `channel` method.
 pub struct AddressReceiver<A: Actor> {
     inner: Arc<Inner<A>>,
+
+    // Reason passed to the exit hooks when the receiver is dropped.
+    exit_reason: Option<ExitReason>,
 }
 
 /// Generate `AddressSenders` for the channel
@@ -154,12 +183,17 @@ struct Inner<A: Actor> {
     // Max buffer size of the channel. If `0` then the channel is unbounded.
     buffer: AtomicUsize,
 
+    // What happens to messages sent while the buffer is full, stored as
+    // `MailboxPolicy as usize`.
+    policy: AtomicUsize,
+
     // Internal channel state. Consists of the number of messages stored in the
     // channel as well as a flag signalling that the channel is closed.
     state: AtomicUsize,
 
-    // Atomic, FIFO queue used to send messages to the receiver.
-    message_queue: Queue<Envelope<A>>,
+    // Atomic, FIFO queues used to send messages to the receiver, one per
+    // message priority, lowest first.
+    message_queues: [Queue<Envelope<A>>; 3],
 
     // Atomic, FIFO queue used to send parked task handles to the receiver.
     parked_queue: Queue<Arc<Mutex<SenderTask>>>,
@@ -167,8 +201,30 @@ struct Inner<A: Actor> {
     // Number of senders in existence.
     num_senders: AtomicUsize,
 
+    // Highest number of queued messages seen.
+    peak: AtomicUsize,
+
+    // Total number of messages queued and taken off the queue.
+    enqueued: AtomicUsize,
+    dequeued: AtomicUsize,
+
     // Handle to the receiver's task.
     recv_task: AtomicWaker,
+
+    // Id of the actor at the receiving end of the channel.
+    id: ActorId,
+
+    // Set when a linked actor asks the receiving actor to stop.
+    stop_requested: AtomicBool,
+
+    // Hooks to run once the receiver is dropped.
+    exit: Mutex<ExitState>,
+}
+
+// Lifecycle of the actor at the receiving end of a channel.
+enum ExitState {
+    Running(Vec<ExitHook>),
+    Exited(ExitReason),
 }
 
 // Struct representation of `Inner::state`.
@@ -228,6 +284,18 @@ impl SenderTask {
     }
 }
 
+// Outcome of reserving room for a new message in the channel.
+enum Slot {
+    // Queue the message, parking the sender if `true`.
+    Queued(bool),
+    // The mailbox is full and the message is silently dropped.
+    Dropped,
+    // The mailbox is full and the message is rejected.
+    Rejected,
+    // The receiver is gone.
+    Closed,
+}
+
 /// Creates an in-memory channel implementation of the `Stream` trait with
 /// bounded capacity.
 ///
@@ -248,11 +316,18 @@ pub fn channel<A: Actor>(buffer: usize) -> (AddressSender<A>, AddressReceiver<A>
 
     let inner = Arc::new(Inner {
         buffer: AtomicUsize::new(buffer),
+        policy: AtomicUsize::new(MailboxPolicy::Backpressure as usize),
         state: AtomicUsize::new(INIT_STATE),
-        message_queue: Queue::new(),
+        message_queues: [Queue::new(), Queue::new(), Queue::new()],
         parked_queue: Queue::new(),
         num_senders: AtomicUsize::new(1),
+        peak: AtomicUsize::new(0),
+        enqueued: AtomicUsize::new(0),
+        dequeued: AtomicUsize::new(0),
         recv_task: AtomicWaker::new(),
+        id: ActorId::next(),
+        stop_requested: AtomicBool::new(false),
+        exit: Mutex::new(ExitState::Running(Vec::new())),
     });
 
     let tx = AddressSender {
@@ -261,7 +336,10 @@ pub fn channel<A: Actor>(buffer: usize) -> (AddressSender<A>, AddressReceiver<A>
         maybe_parked: Arc::new(AtomicBool::new(false)),
     };
 
-    let rx = AddressReceiver { inner };
+    let rx = AddressReceiver {
+        inner,
+        exit_reason: None,
+    };
 
     (tx, rx)
 }
@@ -280,6 +358,16 @@ impl<A: Actor> AddressSender<A> {
         state.is_open
     }
 
+    /// Returns the number of messages queued in the channel.
+    pub fn queued(&self) -> usize {
+        self.inner.queued()
+    }
+
+    /// Returns the channel capacity.
+    pub fn capacity(&self) -> usize {
+        self.inner.buffer.load(Relaxed)
+    }
+
     /// Attempts to send a message on this `Sender<A>` with blocking.
     ///
     /// This function must be called from inside of a task.
@@ -295,20 +383,19 @@ impl<A: Actor> AddressSender<A> {
             return Err(SendError::Full(msg));
         }
 
-        // First, increment the number of messages contained by the channel.
-        // This operation will also atomically determine if the sender task
-        // should be parked.
+        // First, reserve room for the message in the channel. This operation
+        // will also atomically determine if the sender task should be parked.
         //
-        // None is returned in the case that the channel has been closed by the
-        // receiver. This happens when `Receiver::close` is called or the
+        // The channel is closed when `Receiver::close` is called or the
         // receiver is dropped.
-        let park_self = match self.inc_num_messages() {
-            Some(num_messages) => {
-                // receiver is full
-                let buffer = self.inner.buffer.load(Relaxed);
-                buffer != 0 && num_messages >= buffer
+        let park_self = match self.reserve() {
+            Slot::Queued(park_self) => park_self,
+            Slot::Dropped | Slot::Rejected => {
+                let (tx, rx) = oneshot_channel();
+                tx.fail(MailboxError::Overflow);
+                return Ok(rx);
             }
-            None => return Err(SendError::Closed(msg)),
+            Slot::Closed => return Err(SendError::Closed(msg)),
         };
 
         // If the channel has reached capacity, then the sender task needs to
@@ -318,7 +405,7 @@ impl<A: Actor> AddressSender<A> {
         }
         let (tx, rx) = oneshot_channel();
         let env = <A::Context as ToEnvelope<A, M>>::pack(msg, Some(tx));
-        self.queue_push_and_signal(env);
+        self.queue_push_and_signal(env, M::PRIORITY);
         Ok(rx)
     }
 
@@ -335,20 +422,18 @@ impl<A: Actor> AddressSender<A> {
             return Err(SendError::Full(msg));
         }
 
-        let park_self = match self.inc_num_messages() {
-            Some(num_messages) => {
-                // receiver is full
-                let buffer = self.inner.buffer.load(Relaxed);
-                buffer != 0 && num_messages >= buffer
-            }
-            None => return Err(SendError::Closed(msg)),
+        let park_self = match self.reserve() {
+            Slot::Queued(park_self) => park_self,
+            Slot::Dropped => return Ok(()),
+            Slot::Rejected => return Err(SendError::Full(msg)),
+            Slot::Closed => return Err(SendError::Closed(msg)),
         };
 
         if park_self && park {
             self.park();
         }
         let env = <A::Context as ToEnvelope<A, M>>::pack(msg, None);
-        self.queue_push_and_signal(env);
+        self.queue_push_and_signal(env, M::PRIORITY);
         Ok(())
     }
 
@@ -362,18 +447,30 @@ impl<A: Actor> AddressSender<A> {
         M::Result: Send,
         M: Message + Send,
     {
-        if self.inc_num_messages().is_none() {
-            Err(SendError::Closed(msg))
-        } else {
-            // If inc_num_messages returned Some(park_self), then the mailbox is still active.
-            // We ignore the boolean (indicating to park and wait) in the Some, and queue the
+        match self.reserve() {
+            // We ignore the boolean (indicating to park and wait) and queue the
             // message regardless.
-            let env = <A::Context as ToEnvelope<A, M>>::pack(msg, None);
-            self.queue_push_and_signal(env);
-            Ok(())
+            Slot::Queued(_) => {
+                let env = <A::Context as ToEnvelope<A, M>>::pack(msg, None);
+                self.queue_push_and_signal(env, M::PRIORITY);
+                Ok(())
+            }
+            Slot::Dropped => Ok(()),
+            Slot::Rejected => Err(SendError::Full(msg)),
+            Slot::Closed => Err(SendError::Closed(msg)),
         }
     }
 
+    /// Returns the id of the actor at the receiving end of the channel.
+    pub fn id(&self) -> ActorId {
+        self.inner.id
+    }
+
+    /// Returns a type erased link to the receiving actor.
+    pub(crate) fn link(&self) -> Arc<dyn ActorLink> {
+        self.inner.clone()
+    }
+
     /// Downgrade to `WeakAddressSender` which can later be upgraded
     pub fn downgrade(&self) -> WeakAddressSender<A> {
         WeakAddressSender {
@@ -382,17 +479,57 @@ impl<A: Actor> AddressSender<A> {
     }
 
     // Push message to the queue and signal to the receiver
-    fn queue_push_and_signal(&self, msg: Envelope<A>) {
-        // Push the message onto the message queue
-        self.inner.message_queue.push(msg);
+    fn queue_push_and_signal(&self, msg: Envelope<A>, priority: Priority) {
+        // Push the message onto the message queue of its priority
+        self.inner.message_queues[priority as usize].push(msg);
+        self.inner.enqueued.fetch_add(1, Relaxed);
 
         // Signal to the receiver that a message has been enqueued. If the
         // receiver is parked, this will unpark the task.
         self.inner.recv_task.wake();
     }
 
-    // Increment the number of queued messages. Returns if the sender should
-    // block.
+    // Reserve room for a new message according to the mailbox policy.
+    fn reserve(&self) -> Slot {
+        let num_messages = match self.inc_num_messages() {
+            Some(num_messages) => num_messages,
+            None => return Slot::Closed,
+        };
+        let buffer = self.inner.buffer.load(Relaxed);
+
+        let slot = match self.inner.policy() {
+            // receiver is full
+            MailboxPolicy::Backpressure => Slot::Queued(buffer != 0 && num_messages >= buffer),
+            MailboxPolicy::Unbounded => Slot::Queued(false),
+            policy => {
+                // the oldest messages are only evicted when the receiver reads
+                // the mailbox, until then it may hold twice its buffer
+                let limit = match policy {
+                    MailboxPolicy::DropOldest => buffer.saturating_mul(2),
+                    _ => buffer,
+                };
+                if buffer == 0 || num_messages <= limit {
+                    Slot::Queued(false)
+                } else {
+                    // give the reserved slot back
+                    self.inner.state.fetch_sub(1, SeqCst);
+                    if policy == MailboxPolicy::DropNewest {
+                        Slot::Dropped
+                    } else {
+                        Slot::Rejected
+                    }
+                }
+            }
+        };
+
+        if let Slot::Queued(_) = slot {
+            self.inner.peak.fetch_max(num_messages, Relaxed);
+        }
+        slot
+    }
+
+    // Increment the number of queued messages. Returns the new number of
+    // messages, or `None` if the channel is closed.
     fn inc_num_messages(&self) -> Option<usize> {
         let mut curr = self.inner.state.load(SeqCst);
         loop {
@@ -491,6 +628,10 @@ where
     fn connected(&self) -> bool {
         self.connected()
     }
+
+    fn queued(&self) -> usize {
+        self.queued()
+    }
 }
 
 impl<A: Actor> Clone for AddressSender<A> {
@@ -564,6 +705,13 @@ impl<A: Actor> WeakAddressSender<A> {
     pub fn upgrade(&self) -> Option<AddressSender<A>> {
         Weak::upgrade(&self.inner).map(|inner| AddressSenderProducer { inner }.sender())
     }
+
+    /// Returns a type erased link to the receiving actor, or `None` if the
+    /// channel is gone.
+    pub(crate) fn link(&self) -> Option<Arc<dyn ActorLink>> {
+        let inner = self.inner.upgrade()?;
+        Some(inner)
+    }
 }
 
 impl<A, M> WeakSender<M> for WeakAddressSender<A>
@@ -591,6 +739,14 @@ where
 // ===== impl SenderProducer =====
 //
 //
+impl<A: Actor> Clone for AddressSenderProducer<A> {
+    fn clone(&self) -> Self {
+        AddressSenderProducer {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
 impl<A: Actor> AddressSenderProducer<A> {
     /// Are any senders connected
     pub fn connected(&self) -> bool {
@@ -618,6 +774,16 @@ impl<A: Actor> AddressSenderProducer<A> {
         }
     }
 
+    /// Get mailbox statistics
+    pub fn stats(&self) -> MailboxStats {
+        self.inner.stats()
+    }
+
+    /// Set mailbox overflow policy
+    pub fn set_policy(&mut self, policy: MailboxPolicy) {
+        self.inner.set_policy(policy)
+    }
+
     /// Get sender side of the channel
     pub fn sender(&self) -> AddressSender<A> {
         // this code same as Sender::clone
@@ -679,6 +845,11 @@ impl<A: Actor> AddressReceiver<A> {
         }
     }
 
+    /// Sets the mailbox overflow policy.
+    pub fn set_policy(&mut self, policy: MailboxPolicy) {
+        self.inner.set_policy(policy)
+    }
+
     /// Returns the sender side of the channel.
     pub fn sender(&self) -> AddressSender<A> {
         // this code same as Sender::clone
@@ -714,9 +885,37 @@ impl<A: Actor> AddressReceiver<A> {
         }
     }
 
+    /// Returns whether a linked actor asked the receiving actor to stop,
+    /// clearing the request.
+    pub(crate) fn take_stop_request(&self) -> bool {
+        self.inner.stop_requested.load(Relaxed) && self.inner.stop_requested.swap(false, SeqCst)
+    }
+
+    /// Closes the channel. Messages already queued can still be received.
+    pub(crate) fn close(&mut self) {
+        self.inner.set_closed();
+
+        // Wake up any threads waiting as they'll see that we've closed the
+        // channel and will continue on their merry way.
+        while let Some(task) = unsafe { self.inner.parked_queue.pop_spin() } {
+            task.lock().notify();
+        }
+    }
+
+    /// Sets the reason reported to linked actors once the receiver is dropped.
+    pub(crate) fn set_exit_reason(&mut self, reason: ExitReason) {
+        self.exit_reason = Some(reason);
+    }
+
     fn next_message(&mut self) -> Poll<Option<Envelope<A>>> {
-        // Pop off a message
-        match unsafe { self.inner.message_queue.pop_spin() } {
+        // Pop off a message, highest priority first
+        let msg = self
+            .inner
+            .message_queues
+            .iter()
+            .rev()
+            .find_map(|queue| unsafe { queue.pop_spin() });
+        match msg {
             Some(msg) => {
                 // If there are any parked task handles in the parked queue,
                 // pop one and unpark it.
@@ -747,6 +946,30 @@ impl<A: Actor> AddressReceiver<A> {
         }
     }
 
+    // Evict the oldest messages of the lowest priority while the channel holds
+    // more messages than its buffer, if the mailbox policy asks for it.
+    fn evict_overflow(&mut self) {
+        let buffer = self.inner.buffer.load(Relaxed);
+        if buffer == 0 || self.inner.policy() != MailboxPolicy::DropOldest {
+            return;
+        }
+
+        while decode_state(self.inner.state.load(SeqCst)).num_messages > buffer {
+            let msg = self
+                .inner
+                .message_queues
+                .iter()
+                .find_map(|queue| unsafe { queue.pop_spin() });
+            match msg {
+                Some(mut msg) => {
+                    self.dec_num_messages();
+                    msg.fail(MailboxError::Overflow);
+                }
+                None => break,
+            }
+        }
+    }
+
     // Unpark a single task handle if there is one pending in the parked queue
     fn unpark_one(&mut self) {
         if let Some(task) = unsafe { self.inner.parked_queue.pop_spin() } {
@@ -759,6 +982,7 @@ impl<A: Actor> AddressReceiver<A> {
         // unless there's underflow, and we know there's no underflow
         // because number of messages at this point is always > 0.
         self.inner.state.fetch_sub(1, SeqCst);
+        self.inner.dequeued.fetch_add(1, Relaxed);
     }
 }
 
@@ -767,6 +991,7 @@ impl<A: Actor> Stream for AddressReceiver<A> {
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.get_mut();
+        this.evict_overflow();
         match this.next_message() {
             Poll::Ready(msg) => Poll::Ready(msg),
             Poll::Pending => {
@@ -783,14 +1008,7 @@ impl<A: Actor> Stream for AddressReceiver<A> {
 
 impl<A: Actor> Drop for AddressReceiver<A> {
     fn drop(&mut self) {
-        // close
-        self.inner.set_closed();
-
-        // Wake up any threads waiting as they'll see that we've closed the
-        // channel and will continue on their merry way.
-        while let Some(task) = unsafe { self.inner.parked_queue.pop_spin() } {
-            task.lock().notify();
-        }
+        self.close();
 
         // Drain the channel of all pending messages
         loop {
@@ -814,6 +1032,18 @@ impl<A: Actor> Drop for AddressReceiver<A> {
                 }
             }
         }
+
+        // Notify linked actors
+        let reason = self.exit_reason.take().unwrap_or(ExitReason::Normal);
+        let state = mem::replace(
+            &mut *self.inner.exit.lock(),
+            ExitState::Exited(reason.clone()),
+        );
+        if let ExitState::Running(hooks) = state {
+            for hook in hooks {
+                hook(&reason);
+            }
+        }
     }
 }
 
@@ -829,6 +1059,41 @@ impl<A: Actor> Inner<A> {
         MAX_CAPACITY - self.buffer.load(Relaxed)
     }
 
+    fn queued(&self) -> usize {
+        decode_state(self.state.load(SeqCst)).num_messages
+    }
+
+    fn stats(&self) -> MailboxStats {
+        MailboxStats {
+            queued: self.queued(),
+            peak: self.peak.load(Relaxed),
+            enqueued: self.enqueued.load(Relaxed),
+            dequeued: self.dequeued.load(Relaxed),
+            senders: self.num_senders.load(SeqCst),
+        }
+    }
+
+    fn policy(&self) -> MailboxPolicy {
+        match self.policy.load(Relaxed) {
+            0 => MailboxPolicy::Backpressure,
+            1 => MailboxPolicy::DropNewest,
+            2 => MailboxPolicy::DropOldest,
+            3 => MailboxPolicy::Reject,
+            _ => MailboxPolicy::Unbounded,
+        }
+    }
+
+    fn set_policy(&self, policy: MailboxPolicy) {
+        self.policy.store(policy as usize, Relaxed);
+
+        // only the default policy parks senders, wake up all
+        if policy != MailboxPolicy::Backpressure {
+            while let Some(task) = unsafe { self.parked_queue.pop_spin() } {
+                task.lock().notify();
+            }
+        }
+    }
+
     // Clear `open` flag in the state, keep `num_messages` intact.
     fn set_closed(&self) {
         let curr = self.state.load(SeqCst);
@@ -840,6 +1105,33 @@ impl<A: Actor> Inner<A> {
     }
 }
 
+impl<A: Actor> ActorLink for Inner<A> {
+    fn id(&self) -> ActorId {
+        self.id
+    }
+
+    fn stop(&self) {
+        self.stop_requested.store(true, SeqCst);
+        self.recv_task.wake();
+    }
+
+    fn on_exit(&self, hook: ExitHook) {
+        let mut state = self.exit.lock();
+        match *state {
+            ExitState::Running(ref mut hooks) => hooks.push(hook),
+            ExitState::Exited(ref reason) => {
+                let reason = reason.clone();
+                drop(state);
+                hook(&reason);
+            }
+        }
+    }
+
+    fn exited(&self) -> bool {
+        matches!(*self.exit.lock(), ExitState::Exited(_))
+    }
+}
+
 unsafe impl<A: Actor> Send for Inner<A> {}
 unsafe impl<A: Actor> Sync for Inner<A> {}
 
@@ -1,8 +1,11 @@
-use tokio::sync::oneshot::Sender;
+use std::any::TypeId;
 
 use crate::actor::{Actor, AsyncContext};
 use crate::context::Context;
-use crate::handler::{Handler, Message, MessageResponse};
+use crate::contextimpl::AsyncContextParts;
+use crate::handler::{Handler, Message, MessageResponse, OneshotSender};
+
+use super::MailboxError;
 
 /// Converter trait, packs message into a suitable envelope.
 pub trait ToEnvelope<A, M: Message>
@@ -11,12 +14,16 @@ where
     A::Context: ToEnvelope<A, M>,
 {
     /// Pack message into suitable envelope
-    fn pack(msg: M, tx: Option<Sender<M::Result>>) -> Envelope<A>;
+    fn pack(msg: M, tx: Option<OneshotSender<M::Result>>) -> Envelope<A>;
 }
 
 pub trait EnvelopeProxy<A: Actor> {
     /// handle message within new actor and context
     fn handle(&mut self, act: &mut A, ctx: &mut A::Context);
+
+    /// drop message without handling it, failing the request with `err`
+    #[allow(unused_variables)]
+    fn fail(&mut self, err: MailboxError) {}
 }
 
 impl<A, M> ToEnvelope<A, M> for Context<A>
@@ -25,15 +32,15 @@ where
     M: Message + Send + 'static,
     M::Result: Send,
 {
-    fn pack(msg: M, tx: Option<Sender<M::Result>>) -> Envelope<A> {
-        Envelope::new(msg, tx)
+    fn pack(msg: M, tx: Option<OneshotSender<M::Result>>) -> Envelope<A> {
+        Envelope(Box::new(ContextEnvelopeProxy::new(msg, tx)))
     }
 }
 
 pub struct Envelope<A: Actor>(Box<dyn EnvelopeProxy<A> + Send>);
 
 impl<A: Actor> Envelope<A> {
-    pub fn new<M>(msg: M, tx: Option<Sender<M::Result>>) -> Self
+    pub fn new<M>(msg: M, tx: Option<OneshotSender<M::Result>>) -> Self
     where
         A: Handler<M>,
         A::Context: AsyncContext<A>,
@@ -52,6 +59,10 @@ impl<A: Actor> EnvelopeProxy<A> for Envelope<A> {
     fn handle(&mut self, act: &mut A, ctx: &mut <A as Actor>::Context) {
         self.0.handle(act, ctx)
     }
+
+    fn fail(&mut self, err: MailboxError) {
+        self.0.fail(err)
+    }
 }
 
 pub struct SyncEnvelopeProxy<M>
@@ -60,7 +71,7 @@ where
     M::Result: Send,
 {
     msg: Option<M>,
-    tx: Option<Sender<M::Result>>,
+    tx: Option<OneshotSender<M::Result>>,
 }
 
 impl<A, M> EnvelopeProxy<A> for SyncEnvelopeProxy<M>
@@ -71,14 +82,99 @@ where
     A::Context: AsyncContext<A>,
 {
     fn handle(&mut self, act: &mut A, ctx: &mut <A as Actor>::Context) {
-        let tx = self.tx.take();
-        if tx.is_some() && tx.as_ref().unwrap().is_closed() {
-            return;
-        }
+        let tx = match self.tx.take() {
+            Some(tx) if M::CANCEL_ON_DROP && tx.is_closed() => return,
+            // the deadline passed while the message was queued
+            Some(tx) if tx.is_expired() => return tx.fail(MailboxError::Timeout),
+            tx => tx,
+        };
 
         if let Some(msg) = self.msg.take() {
             let fut = <A as Handler<M>>::handle(act, msg, ctx);
             fut.handle(ctx, tx)
         }
     }
+
+    fn fail(&mut self, err: MailboxError) {
+        self.msg.take();
+        if let Some(tx) = self.tx.take() {
+            tx.fail(err);
+        }
+    }
+}
+
+/// Envelope proxy for actors running in a [`Context`], which can stash the
+/// message being handled.
+pub(crate) struct ContextEnvelopeProxy<M>
+where
+    M: Message,
+{
+    msg: Option<M>,
+    tx: Option<OneshotSender<M::Result>>,
+}
+
+impl<M: Message> ContextEnvelopeProxy<M> {
+    pub(crate) fn new(msg: M, tx: Option<OneshotSender<M::Result>>) -> Self {
+        ContextEnvelopeProxy { msg: Some(msg), tx }
+    }
+}
+
+impl<A, M> EnvelopeProxy<A> for ContextEnvelopeProxy<M>
+where
+    M: Message + 'static,
+    A: Actor<Context = Context<A>> + Handler<M>,
+{
+    fn handle(&mut self, act: &mut A, ctx: &mut Context<A>) {
+        let tx = match self.tx.take() {
+            Some(tx) if M::CANCEL_ON_DROP && tx.is_closed() => return,
+            // the deadline passed while the message was queued
+            Some(tx) if tx.is_expired() => return tx.fail(MailboxError::Timeout),
+            tx => tx,
+        };
+
+        if let Some(msg) = self.msg.take() {
+            handle_in_context(act, ctx, msg, tx)
+        }
+    }
+
+    fn fail(&mut self, err: MailboxError) {
+        self.msg.take();
+        if let Some(tx) = self.tx.take() {
+            tx.fail(err);
+        }
+    }
+}
+
+/// Handles `msg` in a [`Context`], with the current behavior if it has a
+/// handler for the message, and stashes it along with `tx` if the handler
+/// asks for it.
+pub(crate) fn handle_in_context<A, M>(
+    act: &mut A,
+    ctx: &mut Context<A>,
+    msg: M,
+    tx: Option<OneshotSender<M::Result>>,
+) where
+    A: Actor<Context = Context<A>> + Handler<M>,
+    M: Message + 'static,
+{
+    ctx.parts().set_handling(Some(TypeId::of::<M>()));
+
+    // the current behavior takes precedence over the static handler
+    let fut = match ctx.parts().behavior().and_then(|b| b.handler::<M>()) {
+        Some(handler) => handler(act, msg, ctx),
+        None => <A as Handler<M>>::handle(act, msg, ctx),
+    };
+
+    ctx.parts().set_handling(None);
+
+    match ctx.parts().take_stashed().map(|msg| msg.downcast::<M>()) {
+        // the handler stashed the message, the response is kept for when
+        // the message gets replayed
+        Some(Ok(msg)) => ctx
+            .parts()
+            .stash(Box::new(ContextEnvelopeProxy::new(*msg, tx))),
+        // only a message of the type being handled gets set aside
+        Some(Err(_)) => unreachable!(),
+        None => fut.handle(ctx, tx),
+    }
 }
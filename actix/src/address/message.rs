@@ -1,15 +1,16 @@
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{self, Poll};
 use std::time::Duration;
 
 use pin_project_lite::pin_project;
 use tokio::sync::oneshot;
 
-use crate::clock::Sleep;
-use crate::handler::Message;
+use crate::clock::{Instant, Sleep};
+use crate::handler::{Message, OneshotReceiver};
 
-use super::channel::{AddressSender, Sender};
+use super::channel::{ActorLink, AddressSender, Sender};
 use super::{MailboxError, SendError};
 
 pub type Request<A, M> = MsgRequest<AddressSender<A>, M>;
@@ -26,8 +27,9 @@ pin_project! {
         M: Send,
         M::Result: Send
     {
-        rx: Option<oneshot::Receiver<M::Result>>,
+        rx: Option<OneshotReceiver<M::Result>>,
         info: Option<(S, M)>,
+        deadline: Option<Instant>,
         #[pin]
         timeout: Option<Sleep>,
     }
@@ -39,10 +41,11 @@ where
     M: Message + Send,
     M::Result: Send,
 {
-    pub(crate) fn new(rx: Option<oneshot::Receiver<M::Result>>, info: Option<(S, M)>) -> Self {
+    pub(crate) fn new(rx: Option<OneshotReceiver<M::Result>>, info: Option<(S, M)>) -> Self {
         Self {
             rx,
             info,
+            deadline: None,
             timeout: None,
         }
     }
@@ -57,6 +60,26 @@ where
         self.timeout = Some(actix_rt::time::sleep(dur));
         self
     }
+
+    /// Set a deadline for handling the message.
+    ///
+    /// Unlike [`timeout`](Self::timeout), which only stops waiting for the
+    /// response, the deadline is attached to the message itself: if the actor
+    /// gets to the message after `deadline`, it is discarded without being
+    /// handled. The request resolves with [`MailboxError::Timeout`] once the
+    /// deadline has passed.
+    ///
+    /// The deadline follows [`clock::Instant`](crate::clock::Instant), so a
+    /// paused or advanced clock is honored.
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        match self.rx {
+            Some(ref rx) => rx.set_deadline(deadline),
+            // the message is stamped once it fits in the mailbox
+            None => self.deadline = Some(deadline),
+        }
+        self.timeout = Some(actix_rt::time::sleep_until(deadline));
+        self
+    }
 }
 
 impl<S, M> Future for MsgRequest<S, M>
@@ -72,10 +95,18 @@ where
 
         if let Some((sender, msg)) = this.info.take() {
             match sender.send(msg) {
-                Ok(rx) => *this.rx = Some(rx),
+                Ok(rx) => {
+                    if let Some(deadline) = *this.deadline {
+                        rx.set_deadline(deadline);
+                    }
+                    *this.rx = Some(rx);
+                }
                 Err(SendError::Full(msg)) => {
                     *this.info = Some((sender, msg));
-                    return Poll::Pending;
+                    return match this.timeout.as_pin_mut() {
+                        Some(timeout) => timeout.poll(cx).map(|_| Err(MailboxError::Timeout)),
+                        None => Poll::Pending,
+                    };
                 }
                 Err(SendError::Closed(_)) => return Poll::Ready(Err(MailboxError::Closed)),
             }
@@ -83,7 +114,7 @@ where
 
         match this.rx {
             Some(rx) => match Pin::new(rx).poll(cx) {
-                Poll::Ready(res) => Poll::Ready(res.map_err(|_| MailboxError::Closed)),
+                Poll::Ready(res) => Poll::Ready(res.unwrap_or(Err(MailboxError::Closed))),
                 Poll::Pending => match this.timeout.as_pin_mut() {
                     Some(timeout) => timeout.poll(cx).map(|_| Err(MailboxError::Timeout)),
                     None => Poll::Pending,
@@ -93,3 +124,37 @@ where
         }
     }
 }
+
+/// A `Future` which resolves once an actor has stopped.
+///
+/// Created by [`Addr::closed`](super::Addr::closed) and
+/// [`WeakAddr::closed`](super::WeakAddr::closed).
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Closed {
+    rx: Option<oneshot::Receiver<()>>,
+}
+
+impl Closed {
+    pub(crate) fn new(link: Option<Arc<dyn ActorLink>>) -> Self {
+        let rx = link.map(|link| {
+            let (tx, rx) = oneshot::channel();
+            link.on_exit(Box::new(move |_| {
+                let _ = tx.send(());
+            }));
+            rx
+        });
+        Closed { rx }
+    }
+}
+
+impl Future for Closed {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        match self.rx {
+            Some(ref mut rx) => Pin::new(rx).poll(cx).map(|_| ()),
+            None => Poll::Ready(()),
+        }
+    }
+}
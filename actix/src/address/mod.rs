@@ -1,4 +1,8 @@
 use std::hash::{Hash, Hasher};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
 use std::{error, fmt};
 
 pub(crate) mod channel;
@@ -9,10 +13,11 @@ mod queue;
 use crate::actor::Actor;
 use crate::handler::{Handler, Message};
 
+pub(crate) use self::envelope::{handle_in_context, ContextEnvelopeProxy};
 pub use self::envelope::{Envelope, EnvelopeProxy, ToEnvelope};
-pub use self::message::{RecipientRequest, Request};
+pub use self::message::{Closed, RecipientRequest, Request};
 
-pub(crate) use self::channel::{AddressReceiver, AddressSenderProducer};
+pub(crate) use self::channel::{ActorLink, AddressReceiver, AddressSenderProducer};
 use self::channel::{AddressSender, Sender, WeakAddressSender, WeakSender};
 
 pub enum SendError<T> {
@@ -22,9 +27,14 @@ pub enum SendError<T> {
 
 #[derive(Clone, Copy)]
 /// The errors that can occur during the message delivery process.
+#[non_exhaustive]
 pub enum MailboxError {
     Closed,
     Timeout,
+    /// The actor panicked before it could respond to the message.
+    Panicked,
+    /// The message was dropped by the mailbox overflow policy.
+    Overflow,
 }
 
 impl fmt::Debug for MailboxError {
@@ -38,6 +48,8 @@ impl fmt::Display for MailboxError {
         match self {
             MailboxError::Closed => write!(fmt, "Mailbox has closed"),
             MailboxError::Timeout => write!(fmt, "Message delivery timed out"),
+            MailboxError::Panicked => write!(fmt, "Actor panicked while handling the message"),
+            MailboxError::Overflow => write!(fmt, "Message dropped by a full mailbox"),
         }
     }
 }
@@ -72,6 +84,19 @@ impl<T> fmt::Display for SendError<T> {
     }
 }
 
+/// Unique identifier of an actor.
+///
+/// The id stays the same when a supervised actor is restarted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ActorId(usize);
+
+impl ActorId {
+    pub(crate) fn next() -> ActorId {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        ActorId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
 /// The address of an actor.
 pub struct Addr<A: Actor> {
     tx: AddressSender<A>,
@@ -148,6 +173,35 @@ impl<A: Actor> Addr<A> {
         self.into()
     }
 
+    /// Returns the id of the actor.
+    pub fn id(&self) -> ActorId {
+        self.tx.id()
+    }
+
+    /// Returns the number of messages queued in the actor's mailbox.
+    pub fn queued(&self) -> usize {
+        self.tx.queued()
+    }
+
+    /// Returns the capacity of the actor's mailbox. `0` means unbounded.
+    pub fn capacity(&self) -> usize {
+        self.tx.capacity()
+    }
+
+    /// Returns a future that resolves once the actor has stopped, after its
+    /// `stopped` hook ran.
+    ///
+    /// Holding this address does not by itself keep the future pending:
+    /// an actor can be stopped while addresses to it are still around.
+    pub fn closed(&self) -> Closed {
+        Closed::new(Some(self.link()))
+    }
+
+    /// Returns a type erased link to the actor.
+    pub(crate) fn link(&self) -> Arc<dyn ActorLink> {
+        self.tx.link()
+    }
+
     /// Returns a downgraded [`WeakAddr`].
     pub fn downgrade(&self) -> WeakAddr<A> {
         WeakAddr {
@@ -207,6 +261,12 @@ impl<A: Actor> WeakAddr<A> {
         }
     }
 
+    /// Returns a future that resolves once the actor has stopped, after its
+    /// `stopped` hook ran.
+    pub fn closed(&self) -> Closed {
+        Closed::new(self.wtx.link())
+    }
+
     pub fn recipient<M: 'static>(self) -> WeakRecipient<M>
     where
         A: Handler<M>,
@@ -290,6 +350,11 @@ where
     pub fn connected(&self) -> bool {
         self.tx.connected()
     }
+
+    /// Returns the number of messages queued in the actor's mailbox.
+    pub fn queued(&self) -> usize {
+        self.tx.queued()
+    }
 }
 
 impl<A: Actor, M: Message + Send + 'static> From<Addr<A>> for Recipient<M>
@@ -0,0 +1,110 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::actor::Actor;
+use crate::handler::{Handler, Message};
+
+type HandlerFn<A, M> =
+    Rc<dyn Fn(&mut A, M, &mut <A as Actor>::Context) -> <A as Handler<M>>::Result>;
+
+/// A set of message handlers that temporarily replace an actor's [`Handler`]
+/// implementations.
+///
+/// Behaviors are installed with [`Context::become_`](crate::Context::become_)
+/// and removed with [`Context::unbecome`](crate::Context::unbecome). While a
+/// behavior is on top of the actor's behavior stack, messages it handles are
+/// dispatched to it; all other messages go to the actor's `Handler` impls.
+///
+/// ```
+/// # use actix::prelude::*;
+///
+/// struct Hello;
+///
+/// impl Message for Hello {
+///     type Result = String;
+/// }
+///
+/// struct Greeter;
+///
+/// impl Actor for Greeter {
+///     type Context = Context<Self>;
+///
+///     fn started(&mut self, ctx: &mut Self::Context) {
+///         ctx.become_(Behavior::new().on(|_, _: Hello, ctx: &mut Context<Self>| {
+///             // answer the first greeting only
+///             ctx.unbecome();
+///             "hello".to_owned()
+///         }));
+///     }
+/// }
+///
+/// impl Handler<Hello> for Greeter {
+///     type Result = String;
+///
+///     fn handle(&mut self, _: Hello, _: &mut Self::Context) -> Self::Result {
+///         "...".to_owned()
+///     }
+/// }
+/// # fn main() {
+/// # let sys = System::new();
+/// # sys.block_on(async {
+/// let addr = Greeter.start();
+/// assert_eq!(addr.send(Hello).await.unwrap(), "hello");
+/// assert_eq!(addr.send(Hello).await.unwrap(), "...");
+/// # });
+/// # }
+/// ```
+pub struct Behavior<A: Actor> {
+    handlers: HashMap<TypeId, Box<dyn Any>>,
+    _actor: std::marker::PhantomData<fn(&mut A)>,
+}
+
+impl<A: Actor> Behavior<A> {
+    /// Creates a behavior that handles no messages.
+    pub fn new() -> Self {
+        Behavior {
+            handlers: HashMap::new(),
+            _actor: std::marker::PhantomData,
+        }
+    }
+
+    /// Handles messages of type `M` with `f` while the behavior is active.
+    pub fn on<M, F>(mut self, f: F) -> Self
+    where
+        A: Handler<M>,
+        M: Message + 'static,
+        F: Fn(&mut A, M, &mut A::Context) -> <A as Handler<M>>::Result + 'static,
+    {
+        let f: HandlerFn<A, M> = Rc::new(f);
+        self.handlers.insert(TypeId::of::<M>(), Box::new(f));
+        self
+    }
+
+    /// Returns the handler for messages of type `M`, if any.
+    pub(crate) fn handler<M>(&self) -> Option<HandlerFn<A, M>>
+    where
+        A: Handler<M>,
+        M: Message + 'static,
+    {
+        self.handlers
+            .get(&TypeId::of::<M>())
+            .and_then(|f| f.downcast_ref::<HandlerFn<A, M>>())
+            .cloned()
+    }
+}
+
+impl<A: Actor> Default for Behavior<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Actor> fmt::Debug for Behavior<A> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Behavior")
+            .field("handlers", &self.handlers.len())
+            .finish()
+    }
+}
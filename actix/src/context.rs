@@ -1,10 +1,12 @@
 use std::fmt;
 
 use crate::actor::{Actor, ActorContext, ActorState, AsyncContext, SpawnHandle};
-use crate::address::{Addr, AddressReceiver};
+use crate::address::{handle_in_context, ActorId, Addr, AddressReceiver, ContextEnvelopeProxy};
+use crate::behavior::Behavior;
 use crate::contextimpl::{AsyncContextParts, ContextFut, ContextParts};
 use crate::fut::ActorFuture;
-use crate::mailbox::Mailbox;
+use crate::handler::{Handler, Message};
+use crate::mailbox::{Mailbox, MailboxPolicy, MailboxStats};
 
 /// An actor execution context.
 pub struct Context<A>
@@ -76,6 +78,15 @@ where
     fn address(&self) -> Addr<A> {
         self.parts.address()
     }
+
+    #[inline]
+    fn handle_message<M>(&mut self, act: &mut A, msg: M)
+    where
+        A: Handler<M>,
+        M: Message + 'static,
+    {
+        handle_in_context(act, self, msg, None)
+    }
 }
 
 impl<A> Context<A>
@@ -186,10 +197,348 @@ where
         self.parts.set_mailbox_capacity(cap)
     }
 
+    /// Sets what happens to messages sent while the mailbox is full.
+    ///
+    /// The default [`MailboxPolicy::Backpressure`] makes `send` wait for room
+    /// but lets `do_send` grow the mailbox without limit. The other policies
+    /// keep the mailbox within its capacity.
+    ///
+    /// ```
+    /// # use actix::prelude::*;
+    /// use actix::MailboxPolicy;
+    ///
+    /// struct MyActor;
+    /// impl Actor for MyActor {
+    ///     type Context = Context<Self>;
+    ///
+    ///     fn started(&mut self, ctx: &mut Self::Context) {
+    ///         ctx.set_mailbox_capacity(100);
+    ///         ctx.set_mailbox_policy(MailboxPolicy::DropOldest);
+    /// #       System::current().stop();
+    ///     }
+    /// }
+    /// # fn main() {
+    /// # let sys = System::new();
+    /// # sys.block_on(async { MyActor.start() });
+    /// # sys.run().unwrap();
+    /// # }
+    /// ```
+    pub fn set_mailbox_policy(&mut self, policy: MailboxPolicy) {
+        self.parts.set_mailbox_policy(policy)
+    }
+
+    /// Sets aside a message, to replay it later with
+    /// [`unstash_all`](Context::unstash_all).
+    ///
+    /// Called from the handler of `msg`, which hands the message over, the
+    /// value returned by the handler is discarded and the sender's request
+    /// stays pending until the replayed message is handled. Any other
+    /// message is replayed like a [`notify`](AsyncContext::notify)
+    /// message, without a response.
+    ///
+    /// Stashed messages do not survive a [`Supervisor`](crate::Supervisor)
+    /// restart: they are dropped, and their requests fail with
+    /// [`MailboxError::Closed`](crate::MailboxError::Closed).
+    ///
+    /// ```
+    /// # use actix::prelude::*;
+    /// struct Query;
+    ///
+    /// impl Message for Query {
+    ///     type Result = usize;
+    /// }
+    ///
+    /// struct Db {
+    ///     ready: bool,
+    /// }
+    ///
+    /// impl Actor for Db {
+    ///     type Context = Context<Self>;
+    ///
+    ///     fn started(&mut self, ctx: &mut Self::Context) {
+    ///         // connect in the background, then replay the queries
+    ///         ctx.run_later(std::time::Duration::from_millis(10), |act, ctx| {
+    ///             act.ready = true;
+    ///             ctx.unstash_all();
+    ///         });
+    ///     }
+    /// }
+    ///
+    /// impl Handler<Query> for Db {
+    ///     type Result = usize;
+    ///
+    ///     fn handle(&mut self, msg: Query, ctx: &mut Self::Context) -> Self::Result {
+    ///         if !self.ready {
+    ///             ctx.stash(msg);
+    ///             return 0;
+    ///         }
+    ///         42
+    ///     }
+    /// }
+    /// # fn main() {
+    /// # let sys = System::new();
+    /// # sys.block_on(async {
+    /// let addr = Db { ready: false }.start();
+    /// assert_eq!(addr.send(Query).await.unwrap(), 42);
+    /// # });
+    /// # }
+    /// ```
+    pub fn stash<M>(&mut self, msg: M)
+    where
+        A: Handler<M>,
+        M: Message + 'static,
+    {
+        if let Some(msg) = self.parts.stash_current(msg) {
+            self.parts
+                .stash(Box::new(ContextEnvelopeProxy::new(msg, None)))
+        }
+    }
+
+    /// Replays all stashed messages, in the order they were stashed, ahead
+    /// of the messages waiting in the mailbox.
+    pub fn unstash_all(&mut self) {
+        self.parts.unstash_all()
+    }
+
+    /// Returns the number of stashed messages.
+    pub fn stashed(&self) -> usize {
+        self.parts.stashed()
+    }
+
+    /// Installs `behavior` on top of the actor's behavior stack.
+    ///
+    /// Until it is removed with [`unbecome`](Context::unbecome), the
+    /// behavior handles the messages it has handlers for, ahead of the
+    /// actor's [`Handler`] impls. This includes messages the actor sends
+    /// itself with [`notify`](AsyncContext::notify) or through a message
+    /// stream. See [`Behavior`] for an example.
+    pub fn become_(&mut self, behavior: Behavior<A>) {
+        self.parts.push_behavior(behavior)
+    }
+
+    /// Removes the behavior on top of the actor's behavior stack, returning
+    /// to the previous one.
+    pub fn unbecome(&mut self) -> Option<Behavior<A>> {
+        self.parts.pop_behavior()
+    }
+
+    /// Returns a snapshot of the mailbox counters.
+    ///
+    /// ```
+    /// # use actix::prelude::*;
+    /// struct MyActor;
+    /// impl Actor for MyActor {
+    ///     type Context = Context<Self>;
+    ///
+    ///     fn started(&mut self, ctx: &mut Self::Context) {
+    ///         let stats = ctx.mailbox_stats();
+    ///         println!("{} queued, {} at most", stats.queued, stats.peak);
+    /// #       System::current().stop();
+    ///     }
+    /// }
+    /// # fn main() {
+    /// # let sys = System::new();
+    /// # sys.block_on(async { MyActor.start() });
+    /// # sys.run().unwrap();
+    /// # }
+    /// ```
+    pub fn mailbox_stats(&self) -> MailboxStats {
+        self.parts.mailbox_stats()
+    }
+
+    /// Catches panics raised by the actor's handlers, spawned futures and
+    /// `started`, `stopping` and `stopped` hooks.
+    ///
+    /// By default a panic unwinds through the arbiter and takes down every
+    /// actor running on it. With this option enabled, the panic is contained:
+    /// the request that was being handled resolves with
+    /// [`MailboxError::Panicked`](crate::MailboxError::Panicked) and the actor
+    /// is terminated. An actor started by a [`Supervisor`](crate::Supervisor)
+    /// is then restarted.
+    ///
+    /// The option applies from the moment it is set, so enabling it in
+    /// `started` covers the rest of that hook. A panic in `stopping` lets the
+    /// actor stop. Panics while the actor is being constructed, for example in
+    /// a supervisor's factory, are not caught.
+    ///
+    /// ```
+    /// # use actix::prelude::*;
+    /// struct MyActor;
+    ///
+    /// impl Actor for MyActor {
+    ///     type Context = Context<Self>;
+    ///
+    ///     fn started(&mut self, ctx: &mut Self::Context) {
+    ///         ctx.set_catch_unwind(true);
+    /// #       System::current().stop();
+    ///     }
+    /// }
+    /// # fn main() {
+    /// # let sys = System::new();
+    /// # sys.block_on(async { MyActor.start() });
+    /// # sys.run().unwrap();
+    /// # }
+    /// ```
+    pub fn set_catch_unwind(&mut self, enable: bool) {
+        self.parts.set_catch_unwind(enable)
+    }
+
+    /// Stops the actor, like [`ActorContext::stop`], reporting `reason` as
+    /// its [`ExitReason`](crate::ExitReason).
+    ///
+    /// The reason is discarded if the actor's `stopping` hook keeps it
+    /// running.
+    pub fn stop_with_reason<R: Into<String>>(&mut self, reason: R) {
+        self.parts.stop_with_reason(reason.into())
+    }
+
     /// Returns whether any addresses are still connected.
     pub fn connected(&self) -> bool {
         self.parts.connected()
     }
+
+    /// Starts a child actor linked to this actor.
+    ///
+    /// The child is asked to stop, going through its `stopping` and `stopped`
+    /// hooks, when this actor stops. Once the child exits, for whatever reason,
+    /// this actor receives a [`ChildTerminated`] message.
+    ///
+    /// ```
+    /// # use actix::prelude::*;
+    /// struct Parent;
+    /// struct Child;
+    ///
+    /// impl Actor for Parent {
+    ///     type Context = Context<Self>;
+    ///
+    ///     fn started(&mut self, ctx: &mut Self::Context) {
+    ///         ctx.spawn_child(|_| Child);
+    ///         ctx.stop();
+    ///     }
+    /// }
+    ///
+    /// impl Handler<ChildTerminated> for Parent {
+    ///     type Result = ();
+    ///
+    ///     fn handle(&mut self, _: ChildTerminated, _: &mut Self::Context) {}
+    /// }
+    ///
+    /// impl Actor for Child {
+    ///     type Context = Context<Self>;
+    ///
+    ///     fn stopped(&mut self, _: &mut Self::Context) {
+    ///         System::current().stop();
+    ///     }
+    /// }
+    /// # fn main() {
+    /// # let sys = System::new();
+    /// # sys.block_on(async { Parent.start() });
+    /// # sys.run().unwrap();
+    /// # }
+    /// ```
+    pub fn spawn_child<C, F>(&mut self, f: F) -> Addr<C>
+    where
+        A: Handler<ChildTerminated>,
+        C: Actor<Context = Context<C>>,
+        F: FnOnce(&mut Context<C>) -> C,
+    {
+        let mut ctx = Context::new();
+        let act = f(&mut ctx);
+        let addr = ctx.address();
+
+        let link = addr.link();
+        let id = link.id();
+        let parent = self.address().downgrade();
+        link.on_exit(Box::new(move |_| {
+            if let Some(parent) = parent.upgrade() {
+                parent.do_send(ChildTerminated { id });
+            }
+        }));
+        self.parts.add_child(link);
+
+        ctx.run(act);
+        addr
+    }
+
+    /// Monitors another actor.
+    ///
+    /// This actor receives a [`Terminated`] message once the watched actor has
+    /// stopped, after its `stopped` hook ran. Unlike [`Addr::connected`],
+    /// this does not depend on whether any addresses of the watched actor
+    /// are still held. Watching an actor that has already stopped delivers
+    /// the message right away.
+    ///
+    /// ```
+    /// # use actix::prelude::*;
+    /// struct Watcher;
+    /// struct Worker;
+    ///
+    /// impl Actor for Worker {
+    ///     type Context = Context<Self>;
+    ///
+    ///     fn started(&mut self, ctx: &mut Self::Context) {
+    ///         ctx.stop();
+    ///     }
+    /// }
+    ///
+    /// impl Actor for Watcher {
+    ///     type Context = Context<Self>;
+    ///
+    ///     fn started(&mut self, ctx: &mut Self::Context) {
+    ///         ctx.watch(&Worker.start());
+    ///     }
+    /// }
+    ///
+    /// impl Handler<Terminated> for Watcher {
+    ///     type Result = ();
+    ///
+    ///     fn handle(&mut self, _: Terminated, _: &mut Self::Context) {
+    ///         System::current().stop();
+    ///     }
+    /// }
+    /// # fn main() {
+    /// # let sys = System::new();
+    /// # let _addr = sys.block_on(async { Watcher.start() });
+    /// # sys.run().unwrap();
+    /// # }
+    /// ```
+    pub fn watch<B>(&mut self, addr: &Addr<B>)
+    where
+        A: Handler<Terminated>,
+        B: Actor,
+    {
+        let id = addr.id();
+        let watcher = self.address().downgrade();
+        addr.link().on_exit(Box::new(move |_| {
+            if let Some(watcher) = watcher.upgrade() {
+                watcher.do_send(Terminated { id });
+            }
+        }));
+    }
+}
+
+/// Message sent to an actor when one of the children started with
+/// [`Context::spawn_child`] exits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChildTerminated {
+    /// Id of the child actor.
+    pub id: ActorId,
+}
+
+impl Message for ChildTerminated {
+    type Result = ();
+}
+
+/// Message sent to an actor when an actor monitored with [`Context::watch`]
+/// stops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Terminated {
+    /// Id of the watched actor.
+    pub id: ActorId,
+}
+
+impl Message for Terminated {
+    type Result = ();
 }
 
 impl<A> AsyncContextParts<A> for Context<A>
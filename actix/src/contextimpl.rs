@@ -1,19 +1,24 @@
+use std::any::{Any, TypeId};
+use std::collections::VecDeque;
 use std::fmt;
 use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use bitflags::bitflags;
-use futures_core::ready;
+use log::error;
 use smallvec::SmallVec;
 
 use crate::actor::{
-    Actor, ActorContext, ActorState, AsyncContext, Running, SpawnHandle, Supervised,
+    Actor, ActorContext, ActorState, AsyncContext, ExitReason, Running, SpawnHandle, Supervised,
 };
-use crate::address::{Addr, AddressSenderProducer};
+use crate::address::{ActorLink, Addr, AddressSenderProducer, EnvelopeProxy};
+use crate::behavior::Behavior;
 use crate::contextitems::ActorWaitItem;
 use crate::fut::ActorFuture;
-use crate::mailbox::Mailbox;
+use crate::mailbox::{Mailbox, MailboxPolicy, MailboxStats};
 
 bitflags! {
     /// internal context state
@@ -45,6 +50,14 @@ where
     wait: SmallVec<[ActorWaitItem<A>; 2]>,
     items: SmallVec<[Item<A>; 3]>,
     handles: SmallVec<[SpawnHandle; 2]>,
+    children: Vec<Arc<dyn ActorLink>>,
+    stash: VecDeque<Box<dyn EnvelopeProxy<A>>>,
+    unstashed: VecDeque<Box<dyn EnvelopeProxy<A>>>,
+    stashed: Option<Box<dyn Any>>,
+    handling: Option<TypeId>,
+    behaviors: Vec<Behavior<A>>,
+    exit_reason: Option<ExitReason>,
+    catch_unwind: bool,
 }
 
 impl<A> fmt::Debug for ContextParts<A>
@@ -55,6 +68,8 @@ where
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt.debug_struct("ContextParts")
             .field("flags", &self.flags)
+            .field("children", &self.children.len())
+            .field("stash", &self.stash.len())
             .finish()
     }
 }
@@ -73,6 +88,14 @@ where
             wait: SmallVec::new(),
             items: SmallVec::new(),
             handles: SmallVec::from_slice(&[SpawnHandle::default(), SpawnHandle::default()]),
+            children: Vec::new(),
+            stash: VecDeque::new(),
+            unstashed: VecDeque::new(),
+            stashed: None,
+            handling: None,
+            behaviors: Vec::new(),
+            exit_reason: None,
+            catch_unwind: false,
         }
     }
 
@@ -88,6 +111,16 @@ where
         }
     }
 
+    #[inline]
+    /// Initiate stop process for actor execution, reporting `reason` as
+    /// the exit reason if the actor stops.
+    pub fn stop_with_reason(&mut self, reason: String) {
+        if self.flags.contains(ContextFlags::RUNNING) {
+            self.exit_reason = Some(ExitReason::Custom(reason));
+            self.stop();
+        }
+    }
+
     #[inline]
     /// Terminate actor execution
     pub fn terminate(&mut self) {
@@ -165,15 +198,117 @@ where
         self.addr.set_capacity(cap);
     }
 
+    #[inline]
+    pub fn mailbox_stats(&self) -> MailboxStats {
+        self.addr.stats()
+    }
+
+    #[inline]
+    pub fn set_mailbox_policy(&mut self, policy: MailboxPolicy) {
+        self.addr.set_policy(policy);
+    }
+
     #[inline]
     pub fn address(&self) -> Addr<A> {
         Addr::new(self.addr.sender())
     }
 
+    #[inline]
+    /// Catch panics raised while the actor handles messages and futures.
+    ///
+    /// A caught panic terminates the actor instead of unwinding through
+    /// the arbiter.
+    pub fn set_catch_unwind(&mut self, enable: bool) {
+        self.catch_unwind = enable;
+    }
+
+    /// Link a child actor to this context. The child gets stopped when the
+    /// actor stops.
+    pub(crate) fn add_child(&mut self, child: Arc<dyn ActorLink>) {
+        self.children.retain(|child| !child.exited());
+        self.children.push(child);
+    }
+
+    /// Set the type of the message being handled, if any.
+    pub(crate) fn set_handling(&mut self, msg: Option<TypeId>) {
+        self.handling = msg;
+    }
+
+    /// Set aside the message being handled. Gives `msg` back if it is not of
+    /// the type being handled, or if a message was set aside already.
+    pub(crate) fn stash_current<M: 'static>(&mut self, msg: M) -> Option<M> {
+        if self.handling == Some(TypeId::of::<M>()) && self.stashed.is_none() {
+            self.stashed = Some(Box::new(msg));
+            None
+        } else {
+            Some(msg)
+        }
+    }
+
+    /// Take the message set aside by the handler of the current message.
+    pub(crate) fn take_stashed(&mut self) -> Option<Box<dyn Any>> {
+        self.stashed.take()
+    }
+
+    /// Add a message to the stash.
+    pub(crate) fn stash(&mut self, env: Box<dyn EnvelopeProxy<A>>) {
+        self.stash.push_back(env);
+    }
+
+    /// Schedule stashed messages for replay, ahead of the mailbox.
+    pub(crate) fn unstash_all(&mut self) {
+        // stashed messages are older than the ones still waiting for replay
+        self.stash.append(&mut self.unstashed);
+        std::mem::swap(&mut self.stash, &mut self.unstashed);
+    }
+
+    /// Number of messages in the stash.
+    pub(crate) fn stashed(&self) -> usize {
+        self.stash.len()
+    }
+
+    /// Push a behavior on top of the behavior stack.
+    pub(crate) fn push_behavior(&mut self, behavior: Behavior<A>) {
+        self.behaviors.push(behavior);
+    }
+
+    /// Pop the behavior on top of the behavior stack.
+    pub(crate) fn pop_behavior(&mut self) -> Option<Behavior<A>> {
+        self.behaviors.pop()
+    }
+
+    /// Number of behaviors on the behavior stack.
+    pub(crate) fn behaviors(&self) -> usize {
+        self.behaviors.len()
+    }
+
+    /// Remove the behaviors above the first `len` ones.
+    pub(crate) fn truncate_behaviors(&mut self, len: usize) {
+        self.behaviors.truncate(len);
+    }
+
+    /// The behavior on top of the behavior stack.
+    pub(crate) fn behavior(&self) -> Option<&Behavior<A>> {
+        self.behaviors.last()
+    }
+
+    /// Ask all linked children to stop.
+    fn stop_children(&mut self) {
+        for child in self.children.drain(..) {
+            child.stop();
+        }
+    }
+
     /// Restart context. Cleanup all futures, except address queue.
     #[inline]
     pub(crate) fn restart(&mut self) {
         self.flags = ContextFlags::RUNNING;
+        self.exit_reason = None;
+        self.stashed = None;
+        self.stash.clear();
+        self.unstashed.clear();
+        self.handling = None;
+        self.behaviors.clear();
         self.wait = SmallVec::new();
         self.items = SmallVec::new();
         self.handles[0] = SpawnHandle::default();
@@ -253,6 +388,14 @@ where
         self.mailbox.address()
     }
 
+    /// Terminate actor execution. Unlike `ContextParts::terminate`, an actor
+    /// that already started is not started again before it stops.
+    #[inline]
+    pub(crate) fn terminate(&mut self) {
+        let started = self.ctx.parts().flags & ContextFlags::STARTED;
+        self.ctx.parts().flags = ContextFlags::STOPPED | started;
+    }
+
     #[inline]
     fn stopping(&mut self) -> bool {
         self.ctx
@@ -273,6 +416,12 @@ where
         }
     }
 
+    /// Are any senders connected
+    #[inline]
+    pub(crate) fn connected(&self) -> bool {
+        self.mailbox.connected()
+    }
+
     /// Restart context. Cleanup all futures, except address queue.
     #[inline]
     pub(crate) fn restart(&mut self) -> bool
@@ -290,6 +439,64 @@ where
         }
     }
 
+    /// Restart context with a new actor instance built by `f`. Cleanup all
+    /// futures, except address queue.
+    #[inline]
+    pub(crate) fn restart_with<F>(&mut self, f: F) -> bool
+    where
+        F: FnOnce(&mut C) -> A,
+    {
+        if self.mailbox.connected() {
+            self.wait = SmallVec::new();
+            self.items = SmallVec::new();
+            self.ctx.parts().restart();
+            self.act = f(&mut self.ctx);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Run actor code, catching a panic if the context is configured to do so
+    /// by the time the panic happens.
+    ///
+    /// Returns `None` if a panic was caught, in which case the actor is terminated.
+    fn guard<F, R>(&mut self, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        match panic::catch_unwind(AssertUnwindSafe(|| f(self))) {
+            Ok(res) => Some(res),
+            Err(err) if !self.ctx.parts().catch_unwind => panic::resume_unwind(err),
+            Err(_) => {
+                error!("Actor panicked, terminating its execution context");
+                self.ctx.parts().exit_reason = Some(ExitReason::Panicked);
+                self.ctx.parts().terminate();
+                None
+            }
+        }
+    }
+
+    /// Stop linked children and run the actor's `stopped` hook.
+    ///
+    /// `reason` is used unless the context recorded a more specific one.
+    fn stopped(&mut self, reason: ExitReason) {
+        let parts = self.ctx.parts();
+        parts.stop_children();
+        let reason = parts.exit_reason.take().unwrap_or(reason);
+        let reason = match self.guard(|this| Actor::stopped(&mut this.act, &mut this.ctx)) {
+            Some(()) => reason,
+            None => ExitReason::Panicked,
+        };
+        self.mailbox.set_exit_reason(reason);
+    }
+
+    /// Run the actor's `stopping` hook. A panicking hook lets the actor stop.
+    fn stopping_hook(&mut self) -> Running {
+        self.guard(|this| Actor::stopping(&mut this.act, &mut this.ctx))
+            .unwrap_or(Running::Stop)
+    }
+
     fn merge(&mut self) -> bool {
         let mut modified = false;
 
@@ -357,7 +564,7 @@ where
 
         if !this.ctx.parts().flags.contains(ContextFlags::STARTED) {
             this.ctx.parts().flags.insert(ContextFlags::STARTED);
-            Actor::started(&mut this.act, &mut this.ctx);
+            this.guard(|this| Actor::started(&mut this.act, &mut this.ctx));
 
             // check cancelled handles, just in case
             if this.merge() {
@@ -365,20 +572,45 @@ where
             }
         }
 
+        // a linked actor asked us to stop
+        if this.mailbox.stop_requested() {
+            this.ctx.parts().stop();
+        }
+
         'outer: loop {
             // check wait futures. order does matter
             // ctx.wait() always add to the back of the list
             // and we always have to check most recent future
             while !this.wait.is_empty() && !this.stopping() {
                 let idx = this.wait.len() - 1;
-                let item = this.wait.last_mut().unwrap();
-                ready!(Pin::new(item).poll(&mut this.act, &mut this.ctx, cx));
-                this.wait.remove(idx);
-                this.merge();
+                let res = this.guard(|this| {
+                    let item = this.wait.last_mut().unwrap();
+                    Pin::new(item).poll(&mut this.act, &mut this.ctx, cx)
+                });
+                match res {
+                    Some(Poll::Ready(())) => {
+                        this.wait.remove(idx);
+                        this.merge();
+                    }
+                    Some(Poll::Pending) => return Poll::Pending,
+                    // actor panicked and got terminated
+                    None => break,
+                }
             }
 
-            // process mailbox
-            this.mailbox.poll(&mut this.act, &mut this.ctx, cx);
+            // process mailbox, replaying unstashed messages first
+            this.guard(|this| {
+                this.mailbox
+                    .poll_with(&mut this.act, &mut this.ctx, cx, |act, ctx| {
+                        match ctx.parts().unstashed.pop_front() {
+                            Some(mut env) => {
+                                env.handle(act, ctx);
+                                true
+                            }
+                            None => false,
+                        }
+                    })
+            });
             if !this.wait.is_empty() && !this.stopping() {
                 continue;
             }
@@ -387,8 +619,13 @@ where
             let mut idx = 0;
             while idx < this.items.len() && !this.stopping() {
                 this.ctx.parts().handles[1] = this.items[idx].0;
-                match Pin::new(&mut this.items[idx].1).poll(&mut this.act, &mut this.ctx, cx) {
-                    Poll::Pending => {
+                let res = this.guard(|this| {
+                    Pin::new(&mut this.items[idx].1).poll(&mut this.act, &mut this.ctx, cx)
+                });
+                match res {
+                    // actor panicked and got terminated
+                    None => break,
+                    Some(Poll::Pending) => {
                         // got new waiting item. merge
                         if this.ctx.waiting() {
                             this.merge();
@@ -419,7 +656,7 @@ where
                             idx += 1;
                         }
                     }
-                    Poll::Ready(()) => {
+                    Some(Poll::Ready(())) => {
                         this.items.swap_remove(idx);
 
                         // got new waiting item. merge
@@ -436,6 +673,11 @@ where
             }
             this.ctx.parts().handles[1] = SpawnHandle::default();
 
+            // replay messages unstashed by spawned futures
+            if !this.ctx.parts().unstashed.is_empty() && !this.ctx.waiting() {
+                continue;
+            }
+
             // merge returns true if context contains new items or handles to be cancelled
             if this.merge() && !this.ctx.parts().flags.contains(ContextFlags::STOPPING) {
                 // if we have no item to process, cancelled handles wouldn't be
@@ -451,25 +693,24 @@ where
             // check state
             if this.ctx.parts().flags.contains(ContextFlags::RUNNING) {
                 // possible stop condition
-                if !this.alive()
-                    && Actor::stopping(&mut this.act, &mut this.ctx) == Running::Stop
-                {
+                if !this.alive() && this.stopping_hook() == Running::Stop {
                     this.ctx.parts().flags = ContextFlags::STOPPED | ContextFlags::STARTED;
-                    Actor::stopped(&mut this.act, &mut this.ctx);
+                    this.stopped(ExitReason::Normal);
                     return Poll::Ready(());
                 }
             } else if this.ctx.parts().flags.contains(ContextFlags::STOPPING) {
-                if Actor::stopping(&mut this.act, &mut this.ctx) == Running::Stop {
+                if this.stopping_hook() == Running::Stop {
                     this.ctx.parts().flags = ContextFlags::STOPPED | ContextFlags::STARTED;
-                    Actor::stopped(&mut this.act, &mut this.ctx);
+                    this.stopped(ExitReason::Stopped);
                     return Poll::Ready(());
                 } else {
                     this.ctx.parts().flags.remove(ContextFlags::STOPPING);
                     this.ctx.parts().flags.insert(ContextFlags::RUNNING);
+                    this.ctx.parts().exit_reason = None;
                     continue;
                 }
             } else if this.ctx.parts().flags.contains(ContextFlags::STOPPED) {
-                Actor::stopped(&mut this.act, &mut this.ctx);
+                this.stopped(ExitReason::Terminated);
                 return Poll::Ready(());
             }
 
@@ -9,7 +9,7 @@ use pin_project_lite::pin_project;
 use crate::actor::{Actor, ActorContext, AsyncContext};
 use crate::clock::Sleep;
 use crate::fut::ActorFuture;
-use crate::handler::{Handler, Message, MessageResponse};
+use crate::handler::{Handler, Message};
 
 pub(crate) struct ActorWaitItem<A: Actor>(Pin<Box<dyn ActorFuture<A, Output = ()>>>);
 
@@ -78,8 +78,7 @@ where
     ) -> Poll<Self::Output> {
         let this = self.project();
         ready!(this.timeout.poll(task));
-        let fut = A::handle(act, this.msg.take().unwrap(), ctx);
-        fut.handle(ctx, None);
+        ctx.handle_message(act, this.msg.take().unwrap());
         Poll::Ready(())
     }
 }
@@ -111,8 +110,7 @@ where
         _: &mut task::Context<'_>,
     ) -> Poll<Self::Output> {
         let this = self.get_mut();
-        let fut = Handler::handle(act, this.msg.take().unwrap(), ctx);
-        fut.handle(ctx, None);
+        ctx.handle_message(act, this.msg.take().unwrap());
         Poll::Ready(())
     }
 }
@@ -148,8 +146,7 @@ where
         let mut this = self.project();
 
         while let Some(msg) = ready!(this.stream.as_mut().poll_next(task)) {
-            let fut = Handler::handle(act, msg, ctx);
-            fut.handle(ctx, None);
+            ctx.handle_message(act, msg);
             if ctx.waiting() {
                 return Poll::Pending;
             }
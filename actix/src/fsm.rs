@@ -0,0 +1,361 @@
+//! Finite state machine actors.
+//!
+//! An [`FsmActor`] keeps its current state in an [`Fsm`] and moves between
+//! states with [`FsmActor::transition`]. Every transition runs the
+//! [`on_exit`](FsmActor::on_exit) hook of the old state and the
+//! [`on_enter`](FsmActor::on_enter) hook of the new one, arms the new state's
+//! timeout and is recorded in the machine's transition log.
+//!
+//! Messages are still handled by regular [`Handler`](crate::Handler) impls, so
+//! a state machine actor is started and addressed like any other actor. A
+//! handler typically matches on [`FsmActor::state`] to decide how the message
+//! is handled in the current state. Alternatively a state can handle messages
+//! with its own [`Behavior`], returned by [`FsmActor::state_behavior`], which
+//! is in place for as long as the machine stays in that state.
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use actix::fsm::{Fsm, FsmActor};
+//! use actix::prelude::*;
+//!
+//! #[derive(Clone, Debug, PartialEq)]
+//! enum Door {
+//!     Closed,
+//!     Open,
+//! }
+//!
+//! struct Push;
+//!
+//! impl Message for Push {
+//!     type Result = ();
+//! }
+//!
+//! struct Turnstile {
+//!     fsm: Fsm<Door>,
+//! }
+//!
+//! impl Actor for Turnstile {
+//!     type Context = Context<Self>;
+//!
+//!     fn started(&mut self, ctx: &mut Self::Context) {
+//!         self.enter_initial(ctx);
+//!     }
+//! }
+//!
+//! impl FsmActor for Turnstile {
+//!     type State = Door;
+//!
+//!     fn fsm(&self) -> &Fsm<Door> {
+//!         &self.fsm
+//!     }
+//!
+//!     fn fsm_mut(&mut self) -> &mut Fsm<Door> {
+//!         &mut self.fsm
+//!     }
+//!
+//!     fn state_timeout(&self, state: &Door) -> Option<Duration> {
+//!         match state {
+//!             Door::Open => Some(Duration::from_secs(5)),
+//!             Door::Closed => None,
+//!         }
+//!     }
+//!
+//!     fn on_state_timeout(&mut self, _: &Door, ctx: &mut Context<Self>) {
+//!         // close the door again if nobody came through
+//!         self.transition(Door::Closed, ctx);
+//!     }
+//! }
+//!
+//! impl Handler<Push> for Turnstile {
+//!     type Result = ();
+//!
+//!     fn handle(&mut self, _: Push, ctx: &mut Context<Self>) {
+//!         match self.state() {
+//!             Door::Closed => self.transition(Door::Open, ctx),
+//!             Door::Open => {}
+//!         }
+//!     }
+//! }
+//! # fn main() {
+//! # System::new().block_on(async {
+//! let addr = Turnstile { fsm: Fsm::new(Door::Closed) }.start();
+//! addr.send(Push).await.unwrap();
+//! # });
+//! # }
+//! ```
+
+use std::collections::{vec_deque, VecDeque};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::actor::{Actor, AsyncContext, SpawnHandle};
+use crate::behavior::Behavior;
+use crate::context::Context;
+use crate::contextimpl::AsyncContextParts;
+
+/// Default number of transitions kept in the log of an [`Fsm`].
+const DEFAULT_LOG_CAPACITY: usize = 16;
+
+/// A single entry of a state machine's transition log.
+#[derive(Clone, Debug)]
+pub struct Transition<S> {
+    /// State the machine left.
+    pub from: S,
+    /// State the machine entered.
+    pub to: S,
+    /// Whether the transition was made from the state's timeout handler.
+    pub timed_out: bool,
+    /// Time of the transition.
+    pub at: Instant,
+}
+
+/// State of a finite state machine actor.
+///
+/// Holds the current state, the pending state timeout and a bounded log of
+/// the most recent transitions.
+pub struct Fsm<S> {
+    state: S,
+    log: VecDeque<Transition<S>>,
+    log_capacity: usize,
+    timeout: Option<SpawnHandle>,
+    timing_out: bool,
+    behavior: Option<usize>,
+}
+
+impl<S> Fsm<S> {
+    /// Creates a state machine in the `initial` state.
+    pub fn new(initial: S) -> Self {
+        Self::with_log_capacity(initial, DEFAULT_LOG_CAPACITY)
+    }
+
+    /// Creates a state machine in the `initial` state that keeps the last
+    /// `capacity` transitions in its log.
+    pub fn with_log_capacity(initial: S, capacity: usize) -> Self {
+        Fsm {
+            state: initial,
+            log: VecDeque::with_capacity(capacity),
+            log_capacity: capacity,
+            timeout: None,
+            timing_out: false,
+            behavior: None,
+        }
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Returns the logged transitions, oldest first.
+    pub fn transitions(&self) -> vec_deque::Iter<'_, Transition<S>> {
+        self.log.iter()
+    }
+
+    /// Forgets the timeout and behavior of the current state.
+    fn reset(&mut self) {
+        self.timeout = None;
+        self.timing_out = false;
+        self.behavior = None;
+    }
+
+    fn record(&mut self, transition: Transition<S>) {
+        if self.log_capacity == 0 {
+            return;
+        }
+        if self.log.len() == self.log_capacity {
+            self.log.pop_front();
+        }
+        self.log.push_back(transition);
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for Fsm<S> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Fsm")
+            .field("state", &self.state)
+            .field("transitions", &self.log.len())
+            .finish()
+    }
+}
+
+/// An actor driven by a finite state machine.
+///
+/// Implementors store an [`Fsm`] and expose it through [`fsm`](Self::fsm)
+/// and [`fsm_mut`](Self::fsm_mut). Call [`enter_initial`](Self::enter_initial)
+/// from [`Actor::started`] to run the initial state's `on_enter` hook and arm
+/// its timeout.
+pub trait FsmActor: Actor<Context = Context<Self>> {
+    /// The machine's states.
+    type State: Clone + fmt::Debug + 'static;
+
+    /// Returns the actor's state machine.
+    fn fsm(&self) -> &Fsm<Self::State>;
+
+    /// Returns the actor's state machine mutably.
+    fn fsm_mut(&mut self) -> &mut Fsm<Self::State>;
+
+    /// Called when the machine enters `state`.
+    fn on_enter(&mut self, state: &Self::State, ctx: &mut Self::Context) {
+        let _ = (state, ctx);
+    }
+
+    /// Called when the machine leaves `state`.
+    fn on_exit(&mut self, state: &Self::State, ctx: &mut Self::Context) {
+        let _ = (state, ctx);
+    }
+
+    /// Returns how long the machine may stay in `state`.
+    ///
+    /// Once the timeout elapses without a transition,
+    /// [`on_state_timeout`](Self::on_state_timeout) is called. By default no
+    /// state times out.
+    fn state_timeout(&self, state: &Self::State) -> Option<Duration> {
+        let _ = state;
+        None
+    }
+
+    /// Returns the behavior handling messages while the machine is in
+    /// `state`.
+    ///
+    /// The behavior is installed when the machine enters `state`, ahead of
+    /// its `on_enter` hook, and removed when the machine leaves it, along
+    /// with any behavior installed on top of it in the meantime. By default
+    /// states have no behavior and messages go to the actor's `Handler`
+    /// impls.
+    ///
+    /// ```
+    /// # use actix::fsm::{Fsm, FsmActor};
+    /// # use actix::prelude::*;
+    /// #[derive(Clone, Debug)]
+    /// enum Door {
+    ///     Closed,
+    ///     Open,
+    /// }
+    ///
+    /// struct Push;
+    ///
+    /// impl Message for Push {
+    ///     type Result = ();
+    /// }
+    ///
+    /// struct Turnstile {
+    ///     fsm: Fsm<Door>,
+    /// }
+    /// # impl Actor for Turnstile {
+    /// #     type Context = Context<Self>;
+    /// #     fn started(&mut self, ctx: &mut Self::Context) {
+    /// #         self.enter_initial(ctx);
+    /// #     }
+    /// # }
+    ///
+    /// impl FsmActor for Turnstile {
+    ///     type State = Door;
+    /// #   fn fsm(&self) -> &Fsm<Door> { &self.fsm }
+    /// #   fn fsm_mut(&mut self) -> &mut Fsm<Door> { &mut self.fsm }
+    ///
+    ///     fn state_behavior(&self, state: &Door) -> Option<Behavior<Self>> {
+    ///         match state {
+    ///             Door::Closed => Some(Behavior::new().on(|act: &mut Self, _: Push, ctx| {
+    ///                 act.transition(Door::Open, ctx)
+    ///             })),
+    ///             Door::Open => None,
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// // pushing an open door does nothing
+    /// impl Handler<Push> for Turnstile {
+    ///     type Result = ();
+    ///
+    ///     fn handle(&mut self, _: Push, _: &mut Context<Self>) {}
+    /// }
+    /// ```
+    fn state_behavior(&self, state: &Self::State) -> Option<Behavior<Self>> {
+        let _ = state;
+        None
+    }
+
+    /// Called when the machine stayed in `state` for longer than its timeout.
+    fn on_state_timeout(&mut self, state: &Self::State, ctx: &mut Self::Context) {
+        let _ = (state, ctx);
+    }
+
+    /// Returns the current state.
+    fn state(&self) -> &Self::State {
+        self.fsm().state()
+    }
+
+    /// Enters the initial state.
+    ///
+    /// Runs the `on_enter` hook of the current state, installs its behavior
+    /// and arms its timeout.
+    ///
+    /// After a [`Supervisor`](crate::Supervisor) restart, `started` runs
+    /// again and the machine re-enters the state it was in. The restart
+    /// already cancelled the state's timeout and removed its behavior, their
+    /// bookkeeping is reset here.
+    fn enter_initial(&mut self, ctx: &mut Self::Context) {
+        self.fsm_mut().reset();
+        let state = self.state().clone();
+        arm_timeout(self, &state, ctx);
+        install_behavior(self, &state, ctx);
+        self.on_enter(&state, ctx);
+    }
+
+    /// Moves the machine to the `to` state.
+    ///
+    /// Cancels the pending state timeout, runs the `on_exit` hook of the
+    /// current state and removes its behavior, records the transition,
+    /// installs the new state's behavior and runs its `on_enter` hook.
+    /// Transitions to the current state run both hooks, re-install the
+    /// behavior and re-arm the state timeout as well.
+    fn transition(&mut self, to: Self::State, ctx: &mut Self::Context) {
+        let fsm = self.fsm_mut();
+        let timed_out = std::mem::replace(&mut fsm.timing_out, false);
+        if let Some(handle) = fsm.timeout.take() {
+            ctx.cancel_future(handle);
+        }
+
+        let from = fsm.state.clone();
+        self.on_exit(&from, ctx);
+
+        let fsm = self.fsm_mut();
+        if let Some(len) = fsm.behavior.take() {
+            ctx.parts().truncate_behaviors(len);
+        }
+        fsm.state = to.clone();
+        fsm.record(Transition {
+            from,
+            to: to.clone(),
+            timed_out,
+            at: Instant::now(),
+        });
+
+        // armed before `on_enter`, so a transition made by the hook cancels it
+        arm_timeout(self, &to, ctx);
+        install_behavior(self, &to, ctx);
+        self.on_enter(&to, ctx);
+    }
+}
+
+fn install_behavior<A: FsmActor>(act: &mut A, state: &A::State, ctx: &mut Context<A>) {
+    if let Some(behavior) = act.state_behavior(state) {
+        act.fsm_mut().behavior = Some(ctx.parts().behaviors());
+        ctx.become_(behavior);
+    }
+}
+
+fn arm_timeout<A: FsmActor>(act: &mut A, state: &A::State, ctx: &mut Context<A>) {
+    if let Some(dur) = act.state_timeout(state) {
+        let handle = ctx.run_later(dur, |act, ctx| {
+            let fsm = act.fsm_mut();
+            fsm.timeout = None;
+            fsm.timing_out = true;
+            let state = fsm.state.clone();
+            act.on_state_timeout(&state, ctx);
+            act.fsm_mut().timing_out = false;
+        });
+        act.fsm_mut().timeout = Some(handle);
+    }
+}
@@ -1,9 +1,18 @@
-use std::{fmt, future::Future, pin::Pin, sync::Arc};
-
-pub use tokio::sync::oneshot::Sender as OneshotSender;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{self, Poll},
+    thread,
+};
+
+use parking_lot::Mutex;
+use tokio::sync::oneshot;
 
 use crate::actor::{Actor, AsyncContext};
-use crate::address::Addr;
+use crate::address::{Addr, MailboxError};
+use crate::clock::Instant;
 use crate::fut::{ActorFuture, ActorFutureExt, LocalBoxActorFuture};
 
 /// Describes how to handle messages of a specific type.
@@ -33,6 +42,53 @@ pub trait Message {
     /// The type of value that this message will resolved with if it is
     /// successful.
     type Result: 'static;
+
+    /// The priority of this message in the receiving actor's mailbox.
+    ///
+    /// Queued messages with a higher priority are handled first. Messages
+    /// of the same priority are handled in the order they were sent.
+    ///
+    /// ```
+    /// # use actix::prelude::*;
+    /// struct Shutdown;
+    ///
+    /// impl Message for Shutdown {
+    ///     type Result = ();
+    ///     const PRIORITY: Priority = Priority::High;
+    /// }
+    /// ```
+    const PRIORITY: Priority = Priority::Normal;
+
+    /// Whether a queued message is discarded once nobody waits for its result.
+    ///
+    /// By default a message is handled even if its
+    /// [`Request`](crate::dev::Request) was dropped before the actor got to
+    /// the message. Messages whose result is only of use to the caller can
+    /// opt in to being skipped instead.
+    ///
+    /// ```
+    /// # use actix::prelude::*;
+    /// struct Lookup(String);
+    ///
+    /// impl Message for Lookup {
+    ///     type Result = Option<String>;
+    ///     const CANCEL_ON_DROP: bool = true;
+    /// }
+    /// ```
+    const CANCEL_ON_DROP: bool = false;
+}
+
+/// Priority of a message in an actor's mailbox.
+///
+/// See [`Message::PRIORITY`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Handled after all other queued messages.
+    Low = 0,
+    /// The default priority.
+    Normal = 1,
+    /// Handled before all other queued messages.
+    High = 2,
 }
 
 /// Allow users to use `Arc<M>` as a message without having to re-impl `Message`
@@ -41,6 +97,8 @@ where
     M: Message,
 {
     type Result = M::Result;
+    const PRIORITY: Priority = M::PRIORITY;
+    const CANCEL_ON_DROP: bool = M::CANCEL_ON_DROP;
 }
 
 /// Allow users to use `Box<M>` as a message without having to re-impl `Message`
@@ -49,6 +107,8 @@ where
     M: Message,
 {
     type Result = M::Result;
+    const PRIORITY: Priority = M::PRIORITY;
+    const CANCEL_ON_DROP: bool = M::CANCEL_ON_DROP;
 }
 
 /// A helper type that implements the [`MessageResponse`] trait.
@@ -537,6 +597,100 @@ SIMPLE_RESULT!(f64);
 SIMPLE_RESULT!(String);
 SIMPLE_RESULT!(bool);
 
+/// The sending half of a message response channel.
+///
+/// A responder is handed to [`MessageResponse::handle`] for every message
+/// that was sent with [`Addr::send`]. If the responder is dropped while the
+/// handling thread is panicking, the requester resolves with
+/// [`MailboxError::Panicked`] instead of [`MailboxError::Closed`].
+pub struct OneshotSender<R> {
+    tx: Option<oneshot::Sender<Result<R, MailboxError>>>,
+    deadline: Arc<Mutex<Option<Instant>>>,
+}
+
+/// The receiving half of a message response channel.
+pub struct OneshotReceiver<R> {
+    rx: oneshot::Receiver<Result<R, MailboxError>>,
+    deadline: Arc<Mutex<Option<Instant>>>,
+}
+
+/// Creates a new message response channel.
+pub(crate) fn oneshot_channel<R>() -> (OneshotSender<R>, OneshotReceiver<R>) {
+    let (tx, rx) = oneshot::channel();
+    let deadline = Arc::new(Mutex::new(None));
+    (
+        OneshotSender {
+            tx: Some(tx),
+            deadline: Arc::clone(&deadline),
+        },
+        OneshotReceiver { rx, deadline },
+    )
+}
+
+impl<R> OneshotSender<R> {
+    /// Sends the response to the requester.
+    ///
+    /// Returns the response back if the requester is no longer waiting for it.
+    pub fn send(mut self, res: R) -> Result<(), R> {
+        match self.tx.take().unwrap().send(Ok(res)) {
+            Ok(()) => Ok(()),
+            Err(Ok(res)) => Err(res),
+            Err(Err(_)) => unreachable!(),
+        }
+    }
+
+    /// Fails the request with `err` instead of responding.
+    pub(crate) fn fail(mut self, err: MailboxError) {
+        let _ = self.tx.take().unwrap().send(Err(err));
+    }
+
+    /// Returns `true` if the requester is no longer waiting for the response.
+    pub fn is_closed(&self) -> bool {
+        match self.tx {
+            Some(ref tx) => tx.is_closed(),
+            None => true,
+        }
+    }
+
+    /// Returns `true` if the request's deadline has passed.
+    pub fn is_expired(&self) -> bool {
+        matches!(*self.deadline.lock(), Some(deadline) if deadline <= Instant::now())
+    }
+}
+
+impl<R> OneshotReceiver<R> {
+    /// Sets the deadline after which the message is no longer handled.
+    pub(crate) fn set_deadline(&self, deadline: Instant) {
+        *self.deadline.lock() = Some(deadline);
+    }
+}
+
+impl<R> Future for OneshotReceiver<R> {
+    type Output = Result<Result<R, MailboxError>, oneshot::error::RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.rx).poll(cx)
+    }
+}
+
+impl<R> Drop for OneshotSender<R> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            if let Some(tx) = self.tx.take() {
+                let _ = tx.send(Err(MailboxError::Panicked));
+            }
+        }
+    }
+}
+
+impl<R> fmt::Debug for OneshotSender<R> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("OneshotSender")
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
 // Helper trait for send one shot message from Option<Sender> type.
 // None and error are ignored.
 trait OneshotSend<M> {
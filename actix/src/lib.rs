@@ -38,6 +38,7 @@
 doc_comment::doctest!("../README.md");
 
 mod actor;
+mod behavior;
 mod context;
 mod contextimpl;
 mod contextitems;
@@ -50,8 +51,10 @@ mod mailbox;
 
 pub mod actors;
 pub mod clock;
+pub mod fsm;
 pub mod fut;
 pub mod io;
+pub mod pool;
 pub mod registry;
 pub mod sync;
 pub mod utils;
@@ -61,22 +64,30 @@ pub use actix_derive::{main, test, Message, MessageResponse};
 pub use actix_rt::{spawn, Arbiter, ArbiterHandle, System, SystemRunner};
 
 pub use crate::actor::{
-    Actor, ActorContext, ActorState, AsyncContext, Running, SpawnHandle, Supervised,
+    Actor, ActorContext, ActorHandle, ActorState, AsyncContext, ExitReason, Running,
+    SpawnHandle, Supervised,
 };
-pub use crate::address::{Addr, MailboxError, Recipient, WeakAddr, WeakRecipient};
-pub use crate::context::Context;
+pub use crate::address::{ActorId, Addr, MailboxError, Recipient, WeakAddr, WeakRecipient};
+pub use crate::behavior::Behavior;
+pub use crate::context::{ChildTerminated, Context, Terminated};
 pub use crate::fut::{
     ActorFuture, ActorFutureExt, ActorStream, ActorStreamExt, ActorTryFuture,
     ActorTryFutureExt, WrapFuture, WrapStream,
 };
 pub use crate::handler::{
-    ActorResponse, AtomicResponse, Handler, Message, MessageResult, Response,
+    ActorResponse, AtomicResponse, Handler, Message, MessageResult, Priority, Response,
     ResponseActFuture, ResponseFuture,
 };
+pub use crate::mailbox::{MailboxPolicy, MailboxStats};
+pub use crate::pool::{ArbiterPool, ScalingPolicy};
 pub use crate::registry::{ArbiterService, Registry, SystemRegistry, SystemService};
 pub use crate::stream::StreamHandler;
-pub use crate::supervisor::Supervisor;
-pub use crate::sync::{SyncArbiter, SyncContext};
+pub use crate::supervisor::{
+    GiveUp, RestartPolicy, Supervisor, SupervisorGroup, SupervisorStrategy,
+};
+pub use crate::sync::{
+    SyncArbiter, SyncArbiterBuilder, SyncContext, SyncPoolHandle, WorkerRequest,
+};
 
 #[doc(hidden)]
 pub use crate::context::ContextFutureSpawner;
@@ -99,20 +110,23 @@ pub mod prelude {
     pub use actix_rt::{Arbiter, ArbiterHandle, System, SystemRunner};
 
     pub use crate::actor::{
-        Actor, ActorContext, ActorState, AsyncContext, Running, SpawnHandle, Supervised,
+        Actor, ActorContext, ActorHandle, ActorState, AsyncContext, ExitReason, Running,
+        SpawnHandle, Supervised,
     };
     pub use crate::address::{
-        Addr, MailboxError, Recipient, RecipientRequest, Request, SendError,
+        ActorId, Addr, MailboxError, Recipient, RecipientRequest, Request, SendError,
     };
-    pub use crate::context::{Context, ContextFutureSpawner};
+    pub use crate::behavior::Behavior;
+    pub use crate::context::{ChildTerminated, Context, ContextFutureSpawner, Terminated};
     pub use crate::fut::{
         ActorFuture, ActorFutureExt, ActorStream, ActorStreamExt, ActorTryFuture,
         ActorTryFutureExt, WrapFuture, WrapStream,
     };
     pub use crate::handler::{
-        ActorResponse, AtomicResponse, Handler, Message, MessageResult, Response,
+        ActorResponse, AtomicResponse, Handler, Message, MessageResult, Priority, Response,
         ResponseActFuture, ResponseFuture,
     };
+    pub use crate::pool::{ArbiterPool, ScalingPolicy};
     pub use crate::registry::{ArbiterService, SystemService};
     pub use crate::stream::StreamHandler;
     pub use crate::supervisor::Supervisor;
@@ -141,7 +155,9 @@ pub mod dev {
 
     pub use crate::prelude::*;
 
-    pub use crate::address::{Envelope, EnvelopeProxy, RecipientRequest, Request, ToEnvelope};
+    pub use crate::address::{
+        Closed, Envelope, EnvelopeProxy, RecipientRequest, Request, ToEnvelope,
+    };
     pub mod channel {
         pub use crate::address::channel::{channel, AddressReceiver, AddressSender};
     }
@@ -4,13 +4,59 @@ use std::{fmt, task};
 
 use futures_core::stream::Stream;
 
-use crate::actor::{Actor, AsyncContext};
+use crate::actor::{Actor, AsyncContext, ExitReason};
 use crate::address::EnvelopeProxy;
 use crate::address::{channel, Addr, AddressReceiver, AddressSenderProducer};
 
 /// Default address channel capacity
 pub const DEFAULT_CAPACITY: usize = 16;
 
+/// What happens to messages sent to an actor whose mailbox is full.
+///
+/// Set with [`Context::set_mailbox_policy`](crate::Context::set_mailbox_policy).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MailboxPolicy {
+    /// `send` waits for room, `try_send` fails with `SendError::Full` and
+    /// `do_send` queues the message regardless of capacity. This is the
+    /// default policy.
+    Backpressure,
+    /// New messages are dropped. Requests made with `send` fail with
+    /// [`MailboxError::Overflow`](crate::MailboxError::Overflow).
+    DropNewest,
+    /// New messages are queued. The oldest queued messages of the lowest
+    /// priority are evicted when the actor next reads its mailbox, failing
+    /// their requests with
+    /// [`MailboxError::Overflow`](crate::MailboxError::Overflow).
+    ///
+    /// Until the actor reads its mailbox, up to twice its capacity messages
+    /// are queued; further messages are rejected like with
+    /// [`Reject`](MailboxPolicy::Reject).
+    DropOldest,
+    /// New messages are rejected. `try_send` and `do_send` fail with
+    /// `SendError::Full`, requests made with `send` fail with
+    /// [`MailboxError::Overflow`](crate::MailboxError::Overflow).
+    Reject,
+    /// Capacity is ignored and senders never wait.
+    Unbounded,
+}
+
+/// A snapshot of an actor's mailbox counters.
+///
+/// Returned by [`Context::mailbox_stats`](crate::Context::mailbox_stats).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MailboxStats {
+    /// Number of messages currently queued.
+    pub queued: usize,
+    /// Highest number of queued messages since the actor started.
+    pub peak: usize,
+    /// Total number of messages queued.
+    pub enqueued: usize,
+    /// Total number of messages taken off the queue, handled or evicted.
+    pub dequeued: usize,
+    /// Number of live addresses and recipients of the actor.
+    pub senders: usize,
+}
+
 pub struct Mailbox<A>
 where
     A: Actor,
@@ -66,6 +112,18 @@ where
         self.msgs.connected()
     }
 
+    /// Returns whether a linked actor asked this actor to stop.
+    #[inline]
+    pub(crate) fn stop_requested(&self) -> bool {
+        self.msgs.take_stop_request()
+    }
+
+    /// Sets the exit reason reported to linked actors.
+    #[inline]
+    pub(crate) fn set_exit_reason(&mut self, reason: ExitReason) {
+        self.msgs.set_exit_reason(reason)
+    }
+
     pub fn address(&self) -> Addr<A> {
         Addr::new(self.msgs.sender())
     }
@@ -75,10 +133,28 @@ where
     }
 
     pub fn poll(&mut self, act: &mut A, ctx: &mut A::Context, task: &mut task::Context<'_>) {
+        self.poll_with(act, ctx, task, |_, _| false)
+    }
+
+    /// Handle incoming messages, giving `replay` a chance to handle a message
+    /// of its own ahead of each message taken from the mailbox.
+    pub(crate) fn poll_with<F>(
+        &mut self,
+        act: &mut A,
+        ctx: &mut A::Context,
+        task: &mut task::Context<'_>,
+        mut replay: F,
+    ) where
+        F: FnMut(&mut A, &mut A::Context) -> bool,
+    {
         #[cfg(feature = "mailbox_assert")]
         let mut n_polls = 0u16;
 
         while !ctx.waiting() {
+            if replay(act, ctx) {
+                continue;
+            }
+
             match Pin::new(&mut self.msgs).poll_next(task) {
                 Poll::Ready(Some(mut msg)) => {
                     msg.handle(act, ctx);
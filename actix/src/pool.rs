@@ -0,0 +1,423 @@
+//! Async actor pools
+//!
+//! [`ArbiterPool`] is the async counterpart of [`SyncArbiter`](crate::SyncArbiter):
+//! it runs one instance of an actor on each of several arbiters, so that an
+//! actor type can make use of more than one core.
+//!
+//! Both kinds of pools can be sized automatically between bounds given by a
+//! [`ScalingPolicy`], see [`ArbiterPool::start_autoscaled`] and
+//! [`SyncArbiter::start_autoscaled`](crate::SyncArbiter::start_autoscaled).
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use actix_rt::{Arbiter, ArbiterHandle};
+use log::debug;
+use parking_lot::RwLock;
+
+use crate::actor::{Actor, ActorContext, AsyncContext};
+use crate::address::{Addr, Request, SendError, ToEnvelope};
+use crate::context::Context;
+use crate::handler::{Handler, Message};
+
+/// Bounds and thresholds for pools that grow and shrink with their load.
+///
+/// Every `interval`, the pool's load is checked:
+///
+/// * if more than `queue_threshold` messages per instance are queued, an
+///   instance is added, up to `max` instances;
+/// * if no messages were queued for `idle_timeout`, an instance is removed,
+///   down to `min` instances.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use actix::ScalingPolicy;
+///
+/// // between 2 and 8 instances, adding one whenever more than 16 messages per
+/// // instance are waiting, and removing one after 30s without queued messages
+/// let policy = ScalingPolicy::new(2, 8)
+///     .queue_threshold(16)
+///     .idle_timeout(Duration::from_secs(30));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ScalingPolicy {
+    min: usize,
+    max: usize,
+    interval: Duration,
+    queue_threshold: usize,
+    idle_timeout: Duration,
+}
+
+impl ScalingPolicy {
+    /// Creates a policy keeping between `min` and `max` instances.
+    ///
+    /// Load is checked every second. An instance is added once more than 4
+    /// messages per instance are queued, and removed after 10 seconds without
+    /// queued messages.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is zero or larger than `max`.
+    pub fn new(min: usize, max: usize) -> Self {
+        assert!(min > 0, "a pool needs at least one instance");
+        assert!(min <= max, "min instances must not exceed max instances");
+
+        ScalingPolicy {
+            min,
+            max,
+            interval: Duration::from_secs(1),
+            queue_threshold: 4,
+            idle_timeout: Duration::from_secs(10),
+        }
+    }
+
+    pub(crate) fn min(&self) -> usize {
+        self.min
+    }
+
+    /// Sets how often the pool's load is checked.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the number of queued messages per instance above which an
+    /// instance is added.
+    pub fn queue_threshold(mut self, threshold: usize) -> Self {
+        self.queue_threshold = threshold;
+        self
+    }
+
+    /// Sets for how long no messages may be queued before an instance is
+    /// removed.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+}
+
+/// A pool that can be resized by a [`Scaler`].
+pub(crate) trait Scalable: Unpin + 'static {
+    /// Returns the number of instances and of queued messages, or `None`
+    /// once the pool has stopped.
+    fn load(&self) -> Option<(usize, usize)>;
+
+    /// Adds an instance.
+    fn grow(&self);
+
+    /// Removes an instance.
+    fn shrink(&self);
+}
+
+/// Actor resizing a pool according to a [`ScalingPolicy`].
+pub(crate) struct Scaler<P> {
+    pool: P,
+    policy: ScalingPolicy,
+    idle_since: Option<Instant>,
+}
+
+impl<P: Scalable> Scaler<P> {
+    pub(crate) fn new(pool: P, policy: ScalingPolicy) -> Self {
+        Scaler {
+            pool,
+            policy,
+            idle_since: None,
+        }
+    }
+
+    fn evaluate(&mut self, ctx: &mut Context<Self>) {
+        let (instances, queued) = match self.pool.load() {
+            Some(load) => load,
+            None => return ctx.stop(),
+        };
+
+        if instances < self.policy.min {
+            debug!("Pool below {} instances, adding one", self.policy.min);
+            self.pool.grow();
+        } else if queued > instances * self.policy.queue_threshold {
+            self.idle_since = None;
+            if instances < self.policy.max {
+                debug!("{} messages queued in pool, adding an instance", queued);
+                self.pool.grow();
+            }
+        } else if queued == 0 {
+            let now = Instant::now();
+            let idle_since = *self.idle_since.get_or_insert(now);
+            if now - idle_since >= self.policy.idle_timeout && instances > self.policy.min {
+                debug!("Pool idle, removing an instance");
+                self.pool.shrink();
+                self.idle_since = Some(now);
+            }
+        } else {
+            self.idle_since = None;
+        }
+    }
+}
+
+impl<P: Scalable> Actor for Scaler<P> {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(self.policy.interval, |act, ctx| act.evaluate(ctx));
+    }
+}
+
+/// A pool of actor instances, each running on its own [`Arbiter`].
+///
+/// The pool is used like an [`Addr`]: every message sent through it is
+/// delivered to the instance with the fewest queued messages. Instances with
+/// equally short mailboxes take turns.
+///
+/// Every instance has its own state; the pool is best suited for actors that
+/// do not need to share state between messages, or that share it through
+/// thread safe types.
+///
+/// The pool's arbiters are stopped by [`stop`](ArbiterPool::stop), or once
+/// the last clone of the pool is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use actix::prelude::*;
+///
+/// struct Hash(Vec<u8>);
+///
+/// impl Message for Hash {
+///     type Result = u64;
+/// }
+///
+/// struct Hasher;
+///
+/// impl Actor for Hasher {
+///     type Context = Context<Self>;
+/// }
+///
+/// impl Handler<Hash> for Hasher {
+///     type Result = u64;
+///
+///     fn handle(&mut self, msg: Hash, _: &mut Self::Context) -> u64 {
+///         msg.0.iter().fold(0, |h, b| h.wrapping_mul(31).wrapping_add(*b as u64))
+///     }
+/// }
+///
+/// # fn main() {
+/// System::new().block_on(async {
+///     // one hasher on each of 4 arbiters
+///     let pool = ArbiterPool::start(4, || Hasher);
+///
+///     let hash = pool.send(Hash(b"actix".to_vec())).await.unwrap();
+///     assert_ne!(hash, 0);
+///
+///     pool.stop();
+/// });
+/// # }
+/// ```
+pub struct ArbiterPool<A: Actor> {
+    inner: Arc<Inner<A>>,
+}
+
+struct Inner<A: Actor> {
+    workers: RwLock<Vec<Worker<A>>>,
+    factory: Arc<dyn Fn() -> A + Send + Sync>,
+    next: AtomicUsize,
+    stopped: AtomicBool,
+}
+
+struct Worker<A: Actor> {
+    addr: Addr<A>,
+    arbiter: ArbiterHandle,
+}
+
+impl<A> ArbiterPool<A>
+where
+    A: Actor<Context = Context<A>>,
+{
+    /// Starts `arbiters` new arbiters and runs an actor created by `factory`
+    /// on each of them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `arbiters` is zero.
+    pub fn start<F>(arbiters: usize, factory: F) -> Self
+    where
+        F: Fn() -> A + Send + Sync + 'static,
+    {
+        assert!(arbiters > 0, "ArbiterPool needs at least one arbiter");
+
+        let pool = ArbiterPool {
+            inner: Arc::new(Inner {
+                workers: RwLock::new(Vec::with_capacity(arbiters)),
+                factory: Arc::new(factory),
+                next: AtomicUsize::new(0),
+                stopped: AtomicBool::new(false),
+            }),
+        };
+        for _ in 0..arbiters {
+            pool.inner.grow();
+        }
+        pool
+    }
+
+    /// Starts a pool that grows and shrinks with its load, as configured by
+    /// `policy`.
+    ///
+    /// The pool starts with the policy's minimum number of arbiters. Its load
+    /// is checked by an actor running on the current arbiter.
+    pub fn start_autoscaled<F>(policy: ScalingPolicy, factory: F) -> Self
+    where
+        F: Fn() -> A + Send + Sync + 'static,
+    {
+        let pool = Self::start(policy.min(), factory);
+        Scaler::new(Arc::downgrade(&pool.inner), policy).start();
+        pool
+    }
+}
+
+impl<A: Actor> ArbiterPool<A> {
+    /// Sends a message to one of the instances, without waiting for the
+    /// response.
+    ///
+    /// See [`Addr::do_send`].
+    pub fn do_send<M>(&self, msg: M)
+    where
+        M: Message + Send,
+        M::Result: Send,
+        A: Handler<M>,
+        A::Context: ToEnvelope<A, M>,
+    {
+        self.with_worker(|addr| addr.do_send(msg))
+    }
+
+    /// Tries to send a message to one of the instances.
+    ///
+    /// See [`Addr::try_send`].
+    pub fn try_send<M>(&self, msg: M) -> Result<(), SendError<M>>
+    where
+        M: Message + Send + 'static,
+        M::Result: Send,
+        A: Handler<M>,
+        A::Context: ToEnvelope<A, M>,
+    {
+        self.with_worker(|addr| addr.try_send(msg))
+    }
+
+    /// Sends a message to one of the instances and asynchronously waits for
+    /// the response.
+    ///
+    /// See [`Addr::send`].
+    pub fn send<M>(&self, msg: M) -> Request<A, M>
+    where
+        M: Message + Send + 'static,
+        M::Result: Send,
+        A: Handler<M>,
+        A::Context: ToEnvelope<A, M>,
+    {
+        self.with_worker(|addr| addr.send(msg))
+    }
+
+    /// Returns the addresses of the instances in the pool.
+    pub fn workers(&self) -> Vec<Addr<A>> {
+        let workers = self.inner.workers.read();
+        workers.iter().map(|worker| worker.addr.clone()).collect()
+    }
+
+    /// Returns `true` if any instance in the pool is still running.
+    pub fn connected(&self) -> bool {
+        let workers = self.inner.workers.read();
+        workers.iter().any(|worker| worker.addr.connected())
+    }
+
+    /// Stops the pool's arbiters, and with them all instances.
+    pub fn stop(&self) {
+        self.inner.stopped.store(true, Ordering::Relaxed);
+        for worker in self.inner.workers.read().iter() {
+            worker.arbiter.stop();
+        }
+    }
+
+    /// Calls `f` with the instance with the fewest queued messages, starting
+    /// the search after the previously picked one.
+    fn with_worker<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&Addr<A>) -> R,
+    {
+        let workers = self.inner.workers.read();
+        let start = self.inner.next.fetch_add(1, Ordering::Relaxed);
+        let worker = (start..start + workers.len())
+            .map(|idx| &workers[idx % workers.len()])
+            .min_by_key(|worker| worker.addr.queued())
+            .unwrap();
+        f(&worker.addr)
+    }
+}
+
+impl<A> Inner<A>
+where
+    A: Actor<Context = Context<A>>,
+{
+    fn grow(&self) {
+        let arbiter = Arbiter::new();
+        let factory = Arc::clone(&self.factory);
+        let addr = A::start_in_arbiter(&arbiter.handle(), move |_| factory());
+        self.workers.write().push(Worker {
+            addr,
+            arbiter: arbiter.handle(),
+        });
+    }
+}
+
+impl<A: Actor> Drop for Inner<A> {
+    fn drop(&mut self) {
+        for worker in self.workers.get_mut().iter() {
+            worker.arbiter.stop();
+        }
+    }
+}
+
+impl<A> Scalable for Weak<Inner<A>>
+where
+    A: Actor<Context = Context<A>>,
+{
+    fn load(&self) -> Option<(usize, usize)> {
+        let inner = self.upgrade()?;
+        if inner.stopped.load(Ordering::Relaxed) {
+            return None;
+        }
+        let workers = inner.workers.read();
+        let queued = workers.iter().map(|worker| worker.addr.queued()).sum();
+        Some((workers.len(), queued))
+    }
+
+    fn grow(&self) {
+        if let Some(inner) = self.upgrade() {
+            inner.grow();
+        }
+    }
+
+    fn shrink(&self) {
+        if let Some(inner) = self.upgrade() {
+            if let Some(worker) = inner.workers.write().pop() {
+                worker.arbiter.stop();
+            }
+        }
+    }
+}
+
+impl<A: Actor> Clone for ArbiterPool<A> {
+    fn clone(&self) -> Self {
+        ArbiterPool {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<A: Actor> fmt::Debug for ArbiterPool<A> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("ArbiterPool")
+            .field("workers", &self.inner.workers.read().len())
+            .finish()
+    }
+}
@@ -0,0 +1,353 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{self, Poll};
+use std::time::Duration;
+
+use futures_core::ready;
+
+use crate::actor::{Actor, AsyncContext, Supervised};
+use crate::address::Addr;
+use crate::clock::{sleep, Sleep};
+use crate::context::Context;
+use crate::contextimpl::ContextFut;
+
+use super::{Factory, RestartPolicy, RestartState, Supervisor};
+
+/// Strategy applied by a [`SupervisorGroup`] when one of its children fails.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SupervisorStrategy {
+    /// Restart only the failed child.
+    OneForOne,
+    /// Restart all children of the group.
+    OneForAll,
+    /// Restart the failed child and all children started after it.
+    RestForOne,
+}
+
+/// How a supervised child finished its execution.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ChildExit {
+    /// The child stopped while its address is still in use.
+    Failed,
+    /// All addresses of the child got dropped.
+    Finished,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ChildState {
+    Running,
+    Stopped,
+    Finished,
+}
+
+/// A child supervised by a group, either an actor or a nested group.
+trait Child {
+    /// Drives the child until it stops.
+    fn poll(&mut self, cx: &mut task::Context<'_>) -> Poll<ChildExit>;
+
+    /// Stops a running child, so it can get restarted.
+    fn terminate(&mut self, cx: &mut task::Context<'_>);
+
+    /// Restarts a stopped child. Returns `false` if the child is not in use anymore.
+    fn restart(&mut self) -> bool;
+}
+
+struct ActorChild<A>
+where
+    A: Supervised + Actor<Context = Context<A>>,
+{
+    fut: ContextFut<A, Context<A>>,
+    factory: Option<Factory<A>>,
+}
+
+impl<A> Child for ActorChild<A>
+where
+    A: Supervised + Actor<Context = Context<A>>,
+{
+    fn poll(&mut self, cx: &mut task::Context<'_>) -> Poll<ChildExit> {
+        ready!(Pin::new(&mut self.fut).poll(cx));
+
+        if self.fut.connected() {
+            Poll::Ready(ChildExit::Failed)
+        } else {
+            Poll::Ready(ChildExit::Finished)
+        }
+    }
+
+    fn terminate(&mut self, cx: &mut task::Context<'_>) {
+        self.fut.terminate();
+        let _ = Pin::new(&mut self.fut).poll(cx);
+    }
+
+    fn restart(&mut self) -> bool {
+        Supervisor::restart(&mut self.fut, &self.factory)
+    }
+}
+
+struct ChildEntry {
+    child: Box<dyn Child>,
+    state: ChildState,
+}
+
+/// A group of supervised actors that get restarted together.
+///
+/// Children are started in the order they were added to the group. When a
+/// child fails, the group restarts it along with its siblings, depending on
+/// the [`SupervisorStrategy`]. Children that have to be stopped for that are
+/// terminated in reverse start order and restarted in start order.
+///
+/// Every failure counts against the group's [`RestartPolicy`]. Once the
+/// restart budget is exhausted, all children get terminated and the group
+/// escalates the failure to its parent group, which handles the whole group
+/// as one of its failed children. A top level group performs the give-up
+/// action of its policy instead.
+///
+/// Children are restarted the same way as by a [`Supervisor`], so every child
+/// has to implement [`Supervised`]. All children run on the arbiter the group
+/// was started on.
+///
+/// # Examples
+///
+/// ```
+/// # use std::time::Duration;
+/// # use actix::prelude::*;
+/// use actix::{RestartPolicy, SupervisorGroup, SupervisorStrategy};
+///
+/// struct Db;
+///
+/// impl Actor for Db {
+///     type Context = Context<Self>;
+/// }
+///
+/// impl actix::Supervised for Db {}
+///
+/// struct Cache {
+///     db: Addr<Db>,
+/// }
+///
+/// impl Actor for Cache {
+///     type Context = Context<Self>;
+/// }
+///
+/// impl actix::Supervised for Cache {}
+///
+/// # fn main() {
+/// #    System::new().block_on(async {
+/// let policy = RestartPolicy::new().max_restarts(3, Duration::from_secs(5));
+/// let mut group = SupervisorGroup::new(SupervisorStrategy::RestForOne, policy);
+///
+/// // the cache gets restarted whenever the database actor fails
+/// let db = group.child(|_| Db);
+/// let cache = group.child(move |_| Cache { db });
+///
+/// group.start();
+/// #         System::current().stop();
+/// # });}
+/// ```
+pub struct SupervisorGroup {
+    strategy: SupervisorStrategy,
+    policy: RestartPolicy,
+    state: RestartState,
+    children: Vec<ChildEntry>,
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl fmt::Debug for SupervisorGroup {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("SupervisorGroup")
+            .field("strategy", &self.strategy)
+            .field("state", &self.state)
+            .field("children", &self.children.len())
+            .finish()
+    }
+}
+
+impl SupervisorGroup {
+    /// Creates an empty group.
+    pub fn new(strategy: SupervisorStrategy, policy: RestartPolicy) -> Self {
+        SupervisorGroup {
+            strategy,
+            state: RestartState::new(policy.clone()),
+            policy,
+            children: Vec::new(),
+            delay: None,
+        }
+    }
+
+    /// Adds a supervised actor to the group, returning its address.
+    ///
+    /// The actor is restarted the same way as by [`Supervisor::start`].
+    pub fn child<A, F>(&mut self, f: F) -> Addr<A>
+    where
+        A: Supervised + Actor<Context = Context<A>>,
+        F: FnOnce(&mut Context<A>) -> A,
+    {
+        let mut ctx = Context::new();
+        let act = f(&mut ctx);
+        self.add_actor(ctx, act, None)
+    }
+
+    /// Adds a supervised actor to the group, returning its address.
+    ///
+    /// The actor is rebuilt by `f` on every restart, the same way as by
+    /// [`Supervisor::start_with_factory`].
+    pub fn child_with_factory<A, F>(&mut self, f: F) -> Addr<A>
+    where
+        A: Supervised + Actor<Context = Context<A>>,
+        F: Fn(&mut Context<A>) -> A + 'static,
+    {
+        let mut ctx = Context::new();
+        let act = f(&mut ctx);
+        self.add_actor(ctx, act, Some(Box::new(f)))
+    }
+
+    /// Adds a nested group.
+    ///
+    /// A nested group that exhausts its restart budget fails as a whole, and
+    /// gets restarted by this group along with a fresh restart budget.
+    pub fn group(&mut self, group: SupervisorGroup) {
+        self.add(Box::new(group));
+    }
+
+    /// Starts the group in the current arbiter.
+    pub fn start(self) {
+        actix_rt::spawn(self);
+    }
+
+    fn add_actor<A>(&mut self, ctx: Context<A>, act: A, factory: Option<Factory<A>>) -> Addr<A>
+    where
+        A: Supervised + Actor<Context = Context<A>>,
+    {
+        let addr = ctx.address();
+        let fut = ctx.into_future(act);
+        self.add(Box::new(ActorChild { fut, factory }));
+        addr
+    }
+
+    fn add(&mut self, child: Box<dyn Child>) {
+        self.children.push(ChildEntry {
+            child,
+            state: ChildState::Running,
+        });
+    }
+
+    /// Terminates running children with index `from` and above, in reverse order.
+    fn terminate_from(&mut self, from: usize, cx: &mut task::Context<'_>) {
+        for entry in self.children[from..].iter_mut().rev() {
+            if entry.state == ChildState::Running {
+                entry.child.terminate(cx);
+                entry.state = ChildState::Stopped;
+            }
+        }
+    }
+
+    /// Restarts stopped children in start order.
+    fn restart_stopped(&mut self) {
+        for entry in self.children.iter_mut() {
+            if entry.state == ChildState::Stopped {
+                entry.state = if entry.child.restart() {
+                    ChildState::Running
+                } else {
+                    ChildState::Finished
+                };
+            }
+        }
+    }
+
+    fn poll_children(&mut self, cx: &mut task::Context<'_>) -> Poll<ChildExit> {
+        loop {
+            // wait for the backoff delay before restarting
+            if let Some(ref mut delay) = self.delay {
+                if delay.as_mut().poll(cx).is_ready() {
+                    self.delay = None;
+                    self.restart_stopped();
+                }
+            }
+
+            let mut failed = None;
+            for (idx, entry) in self.children.iter_mut().enumerate() {
+                if entry.state != ChildState::Running {
+                    continue;
+                }
+                if let Poll::Ready(exit) = entry.child.poll(cx) {
+                    if exit == ChildExit::Failed {
+                        entry.state = ChildState::Stopped;
+                        failed = Some(idx);
+                        break;
+                    }
+                    entry.state = ChildState::Finished;
+                }
+            }
+
+            let idx = match failed {
+                Some(idx) => idx,
+                None => {
+                    let finished = self
+                        .children
+                        .iter()
+                        .all(|entry| entry.state == ChildState::Finished);
+                    return if finished {
+                        Poll::Ready(ChildExit::Finished)
+                    } else {
+                        Poll::Pending
+                    };
+                }
+            };
+
+            match self.state.next_restart() {
+                Some(delay) => {
+                    match self.strategy {
+                        SupervisorStrategy::OneForOne => {}
+                        SupervisorStrategy::OneForAll => self.terminate_from(0, cx),
+                        SupervisorStrategy::RestForOne => self.terminate_from(idx + 1, cx),
+                    }
+
+                    if delay == Duration::default() {
+                        self.restart_stopped();
+                    } else {
+                        self.delay = Some(Box::pin(sleep(delay)));
+                    }
+                }
+                None => {
+                    // escalate
+                    self.delay = None;
+                    self.terminate_from(0, cx);
+                    return Poll::Ready(ChildExit::Failed);
+                }
+            }
+        }
+    }
+}
+
+impl Child for SupervisorGroup {
+    fn poll(&mut self, cx: &mut task::Context<'_>) -> Poll<ChildExit> {
+        self.poll_children(cx)
+    }
+
+    fn terminate(&mut self, cx: &mut task::Context<'_>) {
+        self.delay = None;
+        self.terminate_from(0, cx);
+    }
+
+    fn restart(&mut self) -> bool {
+        self.state = RestartState::new(self.policy.clone());
+        self.restart_stopped();
+        self.children
+            .iter()
+            .any(|entry| entry.state == ChildState::Running)
+    }
+}
+
+#[doc(hidden)]
+impl Future for SupervisorGroup {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if ready!(this.poll_children(cx)) == ChildExit::Failed {
+            this.state.give_up();
+        }
+        Poll::Ready(())
+    }
+}
@@ -0,0 +1,478 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{self, Poll};
+use std::time::Duration;
+
+use actix_rt::{ArbiterHandle, System};
+use futures_core::ready;
+use log::error;
+use pin_project_lite::pin_project;
+
+use crate::actor::{Actor, AsyncContext, Supervised};
+use crate::address::{channel, Addr};
+use crate::clock::{sleep, Instant, Sleep};
+use crate::context::Context;
+use crate::contextimpl::ContextFut;
+use crate::mailbox::DEFAULT_CAPACITY;
+use crate::utils::random;
+
+mod group;
+
+pub use self::group::{SupervisorGroup, SupervisorStrategy};
+
+/// Action taken by a [`Supervisor`] once its actor exhausted the restart budget.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GiveUp {
+    /// Leave the actor stopped.
+    ///
+    /// The actor's mailbox gets closed, so pending and future requests fail
+    /// with [`MailboxError::Closed`](crate::MailboxError::Closed).
+    Stop,
+    /// Stop the current [`System`] with the given exit code.
+    StopSystem(i32),
+}
+
+/// Restart policy of a [`Supervisor`].
+///
+/// The default policy restarts the actor immediately and without limit.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use actix::{GiveUp, RestartPolicy};
+///
+/// // At most 5 restarts per minute, waiting between 100ms and 10s before each
+/// // restart. Stop the whole system if the actor keeps failing.
+/// let policy = RestartPolicy::new()
+///     .max_restarts(5, Duration::from_secs(60))
+///     .backoff(Duration::from_millis(100), Duration::from_secs(10))
+///     .give_up(GiveUp::StopSystem(1));
+/// ```
+#[derive(Clone, Debug)]
+pub struct RestartPolicy {
+    max_restarts: Option<(usize, Duration)>,
+    backoff: Option<(Duration, Duration)>,
+    give_up: GiveUp,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy {
+            max_restarts: None,
+            backoff: None,
+            give_up: GiveUp::Stop,
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Creates a policy that restarts immediately and without limit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows at most `max` restarts within the sliding time window `within`.
+    ///
+    /// Once the actor fails more often, the supervisor gives up.
+    pub fn max_restarts(mut self, max: usize, within: Duration) -> Self {
+        self.max_restarts = Some((max, within));
+        self
+    }
+
+    /// Waits before restarting the actor.
+    ///
+    /// The delay starts at `min` and doubles with every consecutive restart,
+    /// up to `max`. Each delay is randomized by up to half of its length to
+    /// spread out restarts of actors that fail together. Once the actor stays
+    /// up for longer than `max`, the delay starts over at `min`.
+    pub fn backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.backoff = Some((min, max));
+        self
+    }
+
+    /// Sets the action taken once the restart budget is exhausted.
+    ///
+    /// Defaults to [`GiveUp::Stop`].
+    pub fn give_up(mut self, action: GiveUp) -> Self {
+        self.give_up = action;
+        self
+    }
+}
+
+/// Restart bookkeeping for a failing actor.
+#[derive(Debug)]
+pub(crate) struct RestartState {
+    policy: RestartPolicy,
+    restarts: VecDeque<Instant>,
+    attempt: u32,
+}
+
+impl RestartState {
+    pub(crate) fn new(policy: RestartPolicy) -> Self {
+        RestartState {
+            policy,
+            restarts: VecDeque::new(),
+            attempt: 0,
+        }
+    }
+
+    /// Records a failure.
+    ///
+    /// Returns the delay to wait before restarting, or `None` if the restart
+    /// budget is exhausted.
+    pub(crate) fn next_restart(&mut self) -> Option<Duration> {
+        let now = Instant::now();
+
+        if let Some((max, within)) = self.policy.max_restarts {
+            while let Some(at) = self.restarts.front() {
+                if now.duration_since(*at) > within {
+                    self.restarts.pop_front();
+                } else {
+                    break;
+                }
+            }
+            if self.restarts.len() >= max {
+                return None;
+            }
+        }
+
+        let delay = match self.policy.backoff {
+            Some((min, max)) => {
+                if let Some(last) = self.restarts.back() {
+                    if now.duration_since(*last) > max {
+                        self.attempt = 0;
+                    }
+                }
+                let exp = 1u32.checked_shl(self.attempt).unwrap_or(u32::MAX);
+                self.attempt = self.attempt.saturating_add(1);
+                jitter(min.checked_mul(exp).map_or(max, |delay| delay.min(max)))
+            }
+            None => Duration::default(),
+        };
+
+        self.restarts.push_back(now);
+        if self.policy.max_restarts.is_none() && self.restarts.len() > 1 {
+            // only the latest restart is needed for the backoff reset
+            self.restarts.pop_front();
+        }
+
+        Some(delay)
+    }
+
+    /// Performs the give-up action of the policy.
+    pub(crate) fn give_up(&self) {
+        error!("Supervised actor exhausted its restart budget, giving up");
+        if let GiveUp::StopSystem(code) = self.policy.give_up {
+            System::current().stop_with_code(code);
+        }
+    }
+}
+
+// Keeps the first half of the delay and randomizes the second one.
+fn jitter(delay: Duration) -> Duration {
+    let half = delay / 2;
+    let nanos = half.as_nanos() as u64;
+    if nanos == 0 {
+        delay
+    } else {
+        half + Duration::from_nanos(random() % (nanos + 1))
+    }
+}
+
+pin_project! {
+    /// Actor supervisor
+    ///
+    /// A Supervisor manages incoming messages for an actor. In case of actor failure,
+    /// the supervisor creates a new execution context and restarts the actor's lifecycle.
+    /// A Supervisor does not re-create their actor, it just calls the `restarting()`
+    /// method. Use [`Supervisor::start_with_factory`] to build a fresh actor on every
+    /// restart instead.
+    ///
+    /// By default a failed actor is restarted immediately and without limit. Use
+    /// [`Supervisor::start_with_policy`] to limit the number of restarts and to
+    /// wait between them.
+    ///
+    /// Supervisors have the same lifecycle as actors. If all addresses to
+    /// a supervisor gets dropped and its actor does not execute anything, the supervisor
+    /// terminates.
+    ///
+    /// Supervisors can not guarantee that their actors successfully processes incoming
+    /// messages. If the actor fails during message processing, the message can not be
+    /// recovered. The sender would receive an `Err(Cancelled)` error in this situation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use actix::prelude::*;
+    /// #[derive(Message)]
+    /// #[rtype(result = "()")]
+    /// struct Die;
+    ///
+    /// struct MyActor;
+    ///
+    /// impl Actor for MyActor {
+    ///     type Context = Context<Self>;
+    /// }
+    ///
+    /// // To use actor with supervisor actor has to implement `Supervised` trait
+    /// impl actix::Supervised for MyActor {
+    ///     fn restarting(&mut self, ctx: &mut Context<MyActor>) {
+    ///         println!("restarting");
+    ///     }
+    /// }
+    ///
+    /// impl Handler<Die> for MyActor {
+    ///     type Result = ();
+    ///
+    ///     fn handle(&mut self, _: Die, ctx: &mut Context<MyActor>) {
+    ///         ctx.stop();
+    /// #       System::current().stop();
+    ///     }
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut sys = System::new();
+    ///
+    ///     let addr = sys.block_on(async { actix::Supervisor::start(|_| MyActor) });
+    ///     addr.do_send(Die);
+    ///
+    ///     sys.run();
+    /// }
+    /// ```
+    pub struct Supervisor<A>
+    where
+        A: Supervised,
+        A: Actor<Context = Context<A>>
+    {
+        #[pin]
+        fut: ContextFut<A, Context<A>>,
+        factory: Option<Factory<A>>,
+        state: RestartState,
+        #[pin]
+        delay: Option<Sleep>,
+    }
+}
+
+type Factory<A> = Box<dyn Fn(&mut Context<A>) -> A>;
+
+impl<A> fmt::Debug for Supervisor<A>
+where
+    A: Supervised + Actor<Context = Context<A>>,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Supervisor")
+            .field("fut", &self.fut)
+            .field("factory", &self.factory.is_some())
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+impl<A> Supervisor<A>
+where
+    A: Supervised + Actor<Context = Context<A>>,
+{
+    /// Start new supervised actor in current tokio runtime.
+    ///
+    /// Type of returned address depends on variable type. For example to get
+    /// `Addr<Syn, _>` of newly created actor, use explicitly `Addr<Syn,
+    /// _>` type as type of a variable.
+    ///
+    /// ```
+    /// # use actix::prelude::*;
+    /// struct MyActor;
+    ///
+    /// impl Actor for MyActor {
+    ///     type Context = Context<Self>;
+    /// }
+    ///
+    /// # impl actix::Supervised for MyActor {}
+    /// # fn main() {
+    /// #    System::new().block_on(async {
+    /// // Get `Addr` of a MyActor actor
+    /// let addr = actix::Supervisor::start(|_| MyActor);
+    /// #         System::current().stop();
+    /// # });}
+    /// ```
+    pub fn start<F>(f: F) -> Addr<A>
+    where
+        F: FnOnce(&mut A::Context) -> A + 'static,
+        A: Actor<Context = Context<A>>,
+    {
+        Self::start_with_policy(RestartPolicy::default(), f)
+    }
+
+    /// Start new supervised actor in current tokio runtime, restarting it
+    /// according to the given [`RestartPolicy`].
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use actix::prelude::*;
+    /// use actix::RestartPolicy;
+    ///
+    /// struct MyActor;
+    ///
+    /// impl Actor for MyActor {
+    ///     type Context = Context<Self>;
+    /// }
+    ///
+    /// # impl actix::Supervised for MyActor {}
+    /// # fn main() {
+    /// #    System::new().block_on(async {
+    /// let policy = RestartPolicy::new()
+    ///     .max_restarts(3, Duration::from_secs(10))
+    ///     .backoff(Duration::from_millis(50), Duration::from_secs(5));
+    ///
+    /// let addr = actix::Supervisor::start_with_policy(policy, |_| MyActor);
+    /// #         System::current().stop();
+    /// # });}
+    /// ```
+    pub fn start_with_policy<F>(policy: RestartPolicy, f: F) -> Addr<A>
+    where
+        F: FnOnce(&mut A::Context) -> A + 'static,
+        A: Actor<Context = Context<A>>,
+    {
+        // create actor
+        let mut ctx = Context::new();
+        let act = f(&mut ctx);
+        let addr = ctx.address();
+        let fut = ctx.into_future(act);
+
+        // create supervisor
+        actix_rt::spawn(Self::new(fut, None, policy));
+
+        addr
+    }
+
+    /// Start new supervised actor in current tokio runtime, building a fresh
+    /// actor instance with `f` on every restart.
+    ///
+    /// Unlike [`Supervisor::start`], the state of a failed actor does not survive
+    /// the restart, so `Supervised::restarting` is not called. The actor's
+    /// mailbox is kept, so existing addresses stay valid.
+    ///
+    /// ```
+    /// # use actix::prelude::*;
+    /// use actix::RestartPolicy;
+    ///
+    /// struct MyActor {
+    ///     cache: Vec<u8>,
+    /// }
+    ///
+    /// impl Actor for MyActor {
+    ///     type Context = Context<Self>;
+    /// }
+    ///
+    /// # impl actix::Supervised for MyActor {}
+    /// # fn main() {
+    /// #    System::new().block_on(async {
+    /// let addr = actix::Supervisor::start_with_factory(RestartPolicy::default(), |_| MyActor {
+    ///     cache: Vec::new(),
+    /// });
+    /// #         System::current().stop();
+    /// # });}
+    /// ```
+    pub fn start_with_factory<F>(policy: RestartPolicy, f: F) -> Addr<A>
+    where
+        F: Fn(&mut A::Context) -> A + 'static,
+        A: Actor<Context = Context<A>>,
+    {
+        // create actor
+        let mut ctx = Context::new();
+        let act = f(&mut ctx);
+        let addr = ctx.address();
+        let fut = ctx.into_future(act);
+
+        // create supervisor
+        actix_rt::spawn(Self::new(fut, Some(Box::new(f)), policy));
+
+        addr
+    }
+
+    /// Start new supervised actor in arbiter's thread.
+    pub fn start_in_arbiter<F>(sys: &ArbiterHandle, f: F) -> Addr<A>
+    where
+        A: Actor<Context = Context<A>>,
+        F: FnOnce(&mut Context<A>) -> A + Send + 'static,
+    {
+        let (tx, rx) = channel::channel(DEFAULT_CAPACITY);
+
+        sys.spawn_fn(move || {
+            let mut ctx = Context::with_receiver(rx);
+            let act = f(&mut ctx);
+            let fut = ctx.into_future(act);
+
+            actix_rt::spawn(Self::new(fut, None, RestartPolicy::default()));
+        });
+
+        Addr::new(tx)
+    }
+
+    fn new(
+        fut: ContextFut<A, Context<A>>,
+        factory: Option<Factory<A>>,
+        policy: RestartPolicy,
+    ) -> Self {
+        Self {
+            fut,
+            factory,
+            state: RestartState::new(policy),
+            delay: None,
+        }
+    }
+
+    fn restart(fut: &mut ContextFut<A, Context<A>>, factory: &Option<Factory<A>>) -> bool {
+        match factory {
+            Some(factory) => fut.restart_with(|ctx| factory(ctx)),
+            None => fut.restart(),
+        }
+    }
+}
+
+#[doc(hidden)]
+impl<A> Future for Supervisor<A>
+where
+    A: Supervised + Actor<Context = Context<A>>,
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        loop {
+            // wait for the backoff delay before restarting
+            if let Some(delay) = this.delay.as_mut().as_pin_mut() {
+                ready!(delay.poll(cx));
+                this.delay.set(None);
+
+                // stop if context's address is not connected
+                if !Self::restart(&mut this.fut, this.factory) {
+                    return Poll::Ready(());
+                }
+            }
+
+            match this.fut.as_mut().poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(_) => {
+                    // stop if context's address is not connected
+                    if !this.fut.connected() {
+                        return Poll::Ready(());
+                    }
+
+                    match this.state.next_restart() {
+                        Some(delay) if delay == Duration::default() => {
+                            Self::restart(&mut this.fut, this.factory);
+                        }
+                        Some(delay) => this.delay.set(Some(sleep(delay))),
+                        None => {
+                            this.state.give_up();
+                            return Poll::Ready(());
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
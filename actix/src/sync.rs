@@ -6,25 +6,41 @@
 //! Actor type A and B, sharing the same thread pool. You need to create two
 //! [`SyncArbiter`]s and have A and B spawn on unique `SyncArbiter`s respectively.
 //! For more information and examples, see `SyncArbiter`
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::fmt;
 use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
 use std::task::Poll;
+use std::time::{Duration, Instant};
 use std::{task, thread};
 
 use actix_rt::System;
 use crossbeam_channel as cb_channel;
-use futures_core::stream::Stream;
-use log::warn;
-use tokio::sync::oneshot::Sender as SyncSender;
+use futures_core::{stream::Stream, task::__internal::AtomicWaker};
+use log::{error, warn};
+use parking_lot::Mutex;
 
-use crate::actor::{Actor, ActorContext, ActorState, Running};
+use crate::actor::{Actor, ActorContext, ActorState, Running, SpawnHandle};
+use crate::actors::router::RouteKey;
 use crate::address::channel;
 use crate::address::{
-    Addr, AddressReceiver, AddressSenderProducer, Envelope, EnvelopeProxy, ToEnvelope,
+    ActorLink, Addr, AddressReceiver, AddressSenderProducer, Envelope, EnvelopeProxy,
+    MailboxError, Request, SendError, ToEnvelope,
 };
+use crate::clock::Sleep;
 use crate::context::Context;
-use crate::handler::{Handler, Message, MessageResponse};
+use crate::handler::{
+    oneshot_channel, Handler, Message, MessageResponse, OneshotReceiver, OneshotSender,
+    ResponseFuture,
+};
+use crate::pool::{Scalable, Scaler, ScalingPolicy};
 
 /// [`SyncArbiter`] provides the resources for a single Sync Actor to run on a dedicated
 /// thread or threads. This is generally used for CPU bound concurrent workloads. It's
@@ -97,8 +113,10 @@ pub struct SyncArbiter<A>
 where
     A: Actor<Context = SyncContext<A>>,
 {
-    queue: Option<cb_channel::Sender<Envelope<A>>>,
+    workers: Option<Arc<Workers<A>>>,
     msgs: AddressReceiver<A>,
+    /// Message waiting for room in a bounded worker queue.
+    pending: Option<Envelope<A>>,
 }
 
 impl<A> SyncArbiter<A>
@@ -109,32 +127,908 @@ where
     /// Returns a single address of the started actor. A single address is
     /// used to communicate to the actor(s), and messages are handled by
     /// the next available Actor in the `SyncArbiter`.
+    ///
+    /// If an actor panics while handling a message, the request for that
+    /// message fails with [`MailboxError::Panicked`] and the actor is replaced
+    /// by a new one from `factory`, running on a new thread. A worker whose
+    /// actors keep panicking before they receive a message, for example in
+    /// `factory` or in [`Actor::started`], is stopped after 5 attempts.
     pub fn start<F>(threads: usize, factory: F) -> Addr<A>
     where
         F: Fn() -> A + Send + Sync + 'static,
     {
-        let factory = Arc::new(factory);
-        let (sender, receiver) = cb_channel::unbounded();
-        let (tx, rx) = channel::channel(0);
+        Self::builder().threads(threads).start(factory)
+    }
 
-        for _ in 0..threads {
-            let f = Arc::clone(&factory);
-            let sys = System::current();
-            let actor_queue = receiver.clone();
-            let inner_rx = rx.sender_producer();
+    /// Start a new `SyncArbiter` whose number of worker threads grows and
+    /// shrinks with the number of queued messages, as configured by `policy`.
+    ///
+    /// The arbiter starts with the policy's minimum number of threads. Its
+    /// load is checked by an actor running on the current arbiter. Threads
+    /// are removed once they finish the message they are handling.
+    pub fn start_autoscaled<F>(policy: ScalingPolicy, factory: F) -> Addr<A>
+    where
+        F: Fn() -> A + Send + Sync + 'static,
+    {
+        Self::builder().autoscale(policy).start(factory)
+    }
 
-            thread::spawn(move || {
-                System::set_current(sys);
-                SyncContext::new(f, actor_queue, inner_rx).run();
-            });
+    /// Returns a builder to configure a new `SyncArbiter`.
+    ///
+    /// ```
+    /// # use actix::prelude::*;
+    /// # struct SyncActor;
+    /// # impl Actor for SyncActor {
+    /// #     type Context = SyncContext<Self>;
+    /// # }
+    /// # fn main() {
+    /// # System::new().block_on(async {
+    /// // 4 worker threads, at most 100 messages waiting for a worker
+    /// let addr = SyncArbiter::builder()
+    ///     .threads(4)
+    ///     .queue_capacity(100)
+    ///     .start(|| SyncActor);
+    /// # });
+    /// # }
+    /// ```
+    pub fn builder() -> SyncArbiterBuilder<A> {
+        SyncArbiterBuilder {
+            threads: 1,
+            queue_capacity: None,
+            policy: None,
+            thread_name: None,
+            _actor: PhantomData,
         }
+    }
+}
+
+/// Builder for a [`SyncArbiter`], created by [`SyncArbiter::builder`].
+pub struct SyncArbiterBuilder<A> {
+    threads: usize,
+    queue_capacity: Option<usize>,
+    policy: Option<ScalingPolicy>,
+    thread_name: Option<String>,
+    _actor: PhantomData<fn() -> A>,
+}
+
+impl<A> SyncArbiterBuilder<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    /// Sets the number of worker threads. Defaults to 1.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
 
-        System::current().arbiter().spawn(Self {
-            queue: Some(sender),
+    /// Bounds the number of messages waiting for a worker thread.
+    ///
+    /// Once `capacity` messages are waiting, [`Addr::send`] waits for a
+    /// worker to take a message and [`Addr::try_send`] fails with
+    /// [`SendError::Full`](crate::prelude::SendError::Full). By default, the
+    /// queue is unbounded.
+    ///
+    /// Every worker thread also gets an inbox of `capacity` messages for the
+    /// messages sent to it alone, see [`SyncPoolHandle::send_to_worker`].
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = Some(capacity);
+        self
+    }
+
+    /// Grows and shrinks the number of worker threads with the number of
+    /// queued messages, as configured by `policy`.
+    ///
+    /// Overrides the number of threads set with
+    /// [`threads`](Self::threads); the arbiter starts with the policy's
+    /// minimum number of threads.
+    pub fn autoscale(mut self, policy: ScalingPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Names the worker threads `{prefix}-{worker id}`.
+    ///
+    /// By default, worker threads are unnamed.
+    pub fn thread_name(mut self, prefix: impl Into<String>) -> Self {
+        self.thread_name = Some(prefix.into());
+        self
+    }
+
+    /// Starts the `SyncArbiter`, running actors created by `factory`.
+    pub fn start<F>(self, factory: F) -> Addr<A>
+    where
+        F: Fn() -> A + Send + Sync + 'static,
+    {
+        self.start_with_handle(move |_| factory()).address()
+    }
+
+    /// Starts the `SyncArbiter` and returns a [`SyncPoolHandle`] to manage its
+    /// worker threads.
+    ///
+    /// `factory` is called with the id of the worker the actor runs on, see
+    /// [`SyncContext::worker_id`].
+    pub fn start_with_handle<F>(self, factory: F) -> SyncPoolHandle<A>
+    where
+        F: Fn(usize) -> A + Send + Sync + 'static,
+    {
+        let threads = match self.policy {
+            Some(ref policy) => policy.min(),
+            None => self.threads,
+        };
+
+        // with a bounded queue, messages wait in the arbiter's mailbox until a
+        // worker is ready to take them
+        let (sender, receiver, capacity, ready) = match self.queue_capacity {
+            Some(capacity) => {
+                let (sender, receiver) = cb_channel::bounded(threads.max(1));
+                (
+                    sender,
+                    receiver,
+                    capacity,
+                    Some(Arc::new(AtomicWaker::new())),
+                )
+            }
+            None => {
+                let (sender, receiver) = cb_channel::unbounded();
+                (sender, receiver, 0, None)
+            }
+        };
+        let (tx, rx) = channel::channel(capacity);
+
+        let workers = Arc::new(Workers {
+            queue: sender,
+            receiver,
+            factory: Arc::new(factory),
+            address: rx.sender_producer(),
+            threads: Arc::new(Threads {
+                inboxes: Mutex::default(),
+                capacity: self.queue_capacity,
+                retiring: AtomicUsize::new(0),
+                closed: AtomicBool::new(false),
+                exited: AtomicWaker::new(),
+                senders: Mutex::default(),
+            }),
+            thread_name: self.thread_name,
+            min_threads: self.policy.as_ref().map_or(1, ScalingPolicy::min),
+            ready,
+        });
+        for _ in 0..threads {
+            workers.add();
+        }
+
+        let scaler = self
+            .policy
+            .map(|policy| Scaler::new(Arc::downgrade(&workers), policy).start().link());
+
+        let handle = SyncPoolHandle {
+            workers: Arc::downgrade(&workers),
+            threads: Arc::clone(&workers.threads),
+            addr: Addr::new(tx),
+            scaler,
+        };
+
+        System::current().arbiter().spawn(SyncArbiter {
+            workers: Some(workers),
             msgs: rx,
+            pending: None,
         });
 
-        Addr::new(tx)
+        handle
+    }
+}
+
+impl<A> fmt::Debug for SyncArbiterBuilder<A> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("SyncArbiterBuilder")
+            .field("threads", &self.threads)
+            .field("queue_capacity", &self.queue_capacity)
+            .field("policy", &self.policy)
+            .field("thread_name", &self.thread_name)
+            .finish()
+    }
+}
+
+/// The running worker threads of a [`SyncArbiter`].
+struct Threads<A: Actor> {
+    /// Queues of messages sent to a single worker thread, by worker id.
+    inboxes: Mutex<BTreeMap<usize, cb_channel::Sender<Envelope<A>>>>,
+    /// Capacity of each inbox, unbounded if `None`.
+    capacity: Option<usize>,
+    /// Number of worker threads asked to exit that are still running,
+    /// changed under the `inboxes` lock.
+    retiring: AtomicUsize,
+    /// Set under the `inboxes` lock once the pool shuts down.
+    closed: AtomicBool,
+    /// Woken when a worker thread exits.
+    exited: AtomicWaker,
+    /// Requests waiting for room in a full inbox.
+    senders: Mutex<Vec<task::Waker>>,
+}
+
+impl<A: Actor> Threads<A> {
+    /// Creates the inbox of a new worker thread.
+    fn inbox(
+        &self,
+    ) -> (
+        cb_channel::Sender<Envelope<A>>,
+        cb_channel::Receiver<Envelope<A>>,
+    ) {
+        match self.capacity {
+            Some(capacity) => cb_channel::bounded(capacity.max(1)),
+            None => cb_channel::unbounded(),
+        }
+    }
+
+    /// Queues a message for the worker thread with the given id.
+    ///
+    /// Fails with `Full` if the worker's inbox is full, and with `Closed` if
+    /// there is no such worker or if the pool is shutting down.
+    fn deliver<M>(
+        &self,
+        id: usize,
+        msg: M,
+        tx: Option<OneshotSender<M::Result>>,
+    ) -> Result<(), SendError<M>>
+    where
+        A: Actor<Context = SyncContext<A>> + Handler<M>,
+        M: Message + Send + 'static,
+        M::Result: Send,
+    {
+        let inboxes = self.inboxes.lock();
+        let inbox = match inboxes.get(&id) {
+            Some(inbox) if !self.closed.load(Ordering::Relaxed) => inbox,
+            _ => return Err(SendError::Closed(msg)),
+        };
+        // messages are only queued under the lock, the inbox cannot fill up
+        // before the message is sent
+        if inbox.is_full() {
+            return Err(SendError::Full(msg));
+        }
+        let _ = inbox.send(<SyncContext<A> as ToEnvelope<A, M>>::pack(msg, tx));
+        Ok(())
+    }
+
+    /// Wakes `waker` once a worker thread takes a message off its inbox, or
+    /// exits.
+    fn register_sender(&self, waker: &task::Waker) {
+        let mut senders = self.senders.lock();
+        if !senders.iter().any(|sender| sender.will_wake(waker)) {
+            senders.push(waker.clone());
+        }
+    }
+
+    /// Wakes the requests waiting for room in an inbox.
+    fn wake_senders(&self) {
+        let senders = std::mem::take(&mut *self.senders.lock());
+        for sender in senders {
+            sender.wake();
+        }
+    }
+
+    /// Stops accepting messages for single worker threads.
+    fn close(&self) {
+        {
+            let _inboxes = self.inboxes.lock();
+            self.closed.store(true, Ordering::Relaxed);
+        }
+        self.wake_senders();
+    }
+}
+
+/// Future resolving once all worker threads of a [`SyncArbiter`] have exited.
+struct Exited<A: Actor>(Arc<Threads<A>>);
+
+impl<A: Actor> Future for Exited<A> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<()> {
+        self.0.exited.register(cx.waker());
+        if self.0.inboxes.lock().is_empty() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Number of times in a row a worker's actor may panic before receiving a
+/// message, before the worker is stopped.
+const MAX_FAILED_STARTS: usize = 5;
+
+/// The worker threads of a [`SyncArbiter`].
+struct Workers<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    queue: cb_channel::Sender<Envelope<A>>,
+    receiver: cb_channel::Receiver<Envelope<A>>,
+    factory: Arc<dyn Fn(usize) -> A + Send + Sync>,
+    address: AddressSenderProducer<A>,
+    threads: Arc<Threads<A>>,
+    thread_name: Option<String>,
+    /// Number of worker threads `retire` keeps running.
+    min_threads: usize,
+    /// Woken by the worker threads when they take a message off a bounded
+    /// queue.
+    ready: Option<Arc<AtomicWaker>>,
+}
+
+impl<A> Workers<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    /// Starts a new worker thread with the lowest free id, and returns the id.
+    ///
+    /// Returns `None` if the pool is shutting down.
+    fn add(self: &Arc<Self>) -> Option<usize> {
+        let (tx, rx) = self.threads.inbox();
+        let id = {
+            let mut inboxes = self.threads.inboxes.lock();
+            if self.threads.closed.load(Ordering::Relaxed) {
+                return None;
+            }
+            let id = (0..).find(|id| !inboxes.contains_key(id)).unwrap();
+            inboxes.insert(id, tx);
+            id
+        };
+        self.spawn(id, rx, 0);
+        Some(id)
+    }
+
+    /// Returns the id of the worker thread handling messages with `key`.
+    ///
+    /// Every worker gets a score from the key and its id, and the worker with
+    /// the highest score is picked. Adding or removing a worker only moves the
+    /// keys of that worker.
+    fn worker_for_key(&self, key: u64) -> Option<usize> {
+        let inboxes = self.threads.inboxes.lock();
+        inboxes.keys().copied().max_by_key(|id| {
+            let mut hasher = DefaultHasher::new();
+            (key, *id).hash(&mut hasher);
+            hasher.finish()
+        })
+    }
+
+    /// Asks one of the worker threads to exit, after the messages already
+    /// handed to the worker threads.
+    ///
+    /// Returns `false` if only `min_threads` worker threads would be left
+    /// running, or if the hand-off queue is bounded and full.
+    fn retire(&self) -> bool {
+        let inboxes = self.threads.inboxes.lock();
+        let retiring = self.threads.retiring.load(Ordering::Relaxed);
+        if inboxes.len() - retiring <= self.min_threads {
+            return false;
+        }
+
+        // the first worker thread to receive the envelope exits
+        let sent = self
+            .queue
+            .try_send(Envelope::with_proxy(Box::new(Retire)))
+            .is_ok();
+        if sent {
+            self.threads.retiring.store(retiring + 1, Ordering::Relaxed);
+        }
+        sent
+    }
+
+    /// Starts the worker thread with the given id.
+    ///
+    /// If the actor panics, the thread exits and a new one is started in its
+    /// place, with a new actor from the factory. `failures` counts the
+    /// previous threads of this worker that panicked before receiving a
+    /// message; the worker is given up once it reaches [`MAX_FAILED_STARTS`].
+    fn spawn(
+        self: &Arc<Self>,
+        id: usize,
+        inbox: cb_channel::Receiver<Envelope<A>>,
+        failures: usize,
+    ) {
+        let factory = Arc::clone(&self.factory);
+        let sys = System::current();
+        let actor_queue = self.receiver.clone();
+        let actor_inbox = inbox.clone();
+        let address = self.address.clone();
+        let threads = Arc::clone(&self.threads);
+        let actor_threads = Arc::clone(&self.threads);
+        let ready = self.ready.clone();
+        let workers = Arc::downgrade(self);
+
+        let mut builder = thread::Builder::new();
+        if let Some(ref prefix) = self.thread_name {
+            builder = builder.name(format!("{}-{}", prefix, id));
+        }
+        builder
+            .spawn(move || {
+                System::set_current(sys);
+                // kept outside of the closure to find out whether the actor
+                // received a message before panicking
+                let mut ctx = None;
+                let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                    ctx.get_or_insert_with(|| {
+                        SyncContext::new(
+                            id,
+                            factory,
+                            actor_queue,
+                            actor_inbox,
+                            actor_threads,
+                            address,
+                            ready,
+                        )
+                    })
+                    .run()
+                }));
+
+                if res.is_err() {
+                    let failures = match ctx {
+                        Some(ref ctx) if ctx.received => 0,
+                        _ => failures + 1,
+                    };
+                    if failures >= MAX_FAILED_STARTS {
+                        error!(
+                            "Sync actor panicked {} times in a row before receiving a message, \
+                             stopping worker {}",
+                            failures, id
+                        );
+                    } else if let Some(workers) = workers.upgrade() {
+                        // the arbiter is gone if the workers are, nothing to replace
+                        error!("Sync actor panicked, restarting it on a new thread");
+                        return workers.spawn(id, inbox, failures);
+                    }
+                }
+                {
+                    let mut inboxes = threads.inboxes.lock();
+                    inboxes.remove(&id);
+                    if matches!(ctx, Some(ref ctx) if ctx.retiring) {
+                        threads.retiring.fetch_sub(1, Ordering::Relaxed);
+                    }
+                }
+                threads.exited.wake();
+                // requests waiting for room in the inbox fail
+                threads.wake_senders();
+            })
+            .expect("failed to spawn sync worker thread");
+    }
+}
+
+impl<A> Scalable for Weak<Workers<A>>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    fn load(&self) -> Option<(usize, usize)> {
+        let workers = self.upgrade()?;
+        if workers.threads.closed.load(Ordering::Relaxed) {
+            return None;
+        }
+        let threads = workers.threads.inboxes.lock().len();
+        let queued = workers.queue.len() + workers.address.stats().queued;
+        Some((threads, queued))
+    }
+
+    fn grow(&self) {
+        if let Some(workers) = self.upgrade() {
+            workers.add();
+        }
+    }
+
+    fn shrink(&self) {
+        if let Some(workers) = self.upgrade() {
+            workers.retire();
+        }
+    }
+}
+
+/// Handle to the worker threads of a [`SyncArbiter`], returned by
+/// [`SyncArbiterBuilder::start_with_handle`].
+///
+/// Like an [`Addr`], the handle keeps the arbiter running. Once the handle and
+/// all addresses of the pool are dropped, or once the pool is
+/// [shut down](Self::shutdown), the worker threads stop.
+///
+/// # Examples
+///
+/// ```
+/// use actix::prelude::*;
+///
+/// struct Worker {
+///     id: usize,
+/// }
+///
+/// impl Actor for Worker {
+///     type Context = SyncContext<Self>;
+/// }
+///
+/// # fn main() {
+/// System::new().block_on(async {
+///     let pool = SyncArbiter::builder()
+///         .threads(2)
+///         .thread_name("worker")
+///         .start_with_handle(|id| Worker { id });
+///     let addr = pool.address();
+///
+///     // a third worker, with id 2
+///     assert_eq!(pool.add_worker(), Some(2));
+///     assert_eq!(pool.workers(), 3);
+/// #   System::current().stop();
+/// });
+/// # }
+/// ```
+pub struct SyncPoolHandle<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    workers: Weak<Workers<A>>,
+    threads: Arc<Threads<A>>,
+    addr: Addr<A>,
+    /// The scaler of an autoscaled pool.
+    scaler: Option<Arc<dyn ActorLink>>,
+}
+
+impl<A> SyncPoolHandle<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    /// Returns the address of the pool.
+    pub fn address(&self) -> Addr<A> {
+        self.addr.clone()
+    }
+
+    /// Returns the number of running worker threads.
+    pub fn workers(&self) -> usize {
+        self.threads.inboxes.lock().len()
+    }
+
+    /// Starts a new worker thread and returns its worker id.
+    ///
+    /// The new worker takes the lowest id not used by a running worker.
+    /// Returns `None` if the arbiter has stopped, or is
+    /// [shutting down](Self::shutdown).
+    pub fn add_worker(&self) -> Option<usize> {
+        self.workers.upgrade().and_then(|workers| workers.add())
+    }
+
+    /// Stops one worker thread.
+    ///
+    /// The request is queued behind the messages the arbiter already handed
+    /// to its worker threads, and the first worker to take it off the queue
+    /// exits. Without a [`queue_capacity`](SyncArbiterBuilder::queue_capacity),
+    /// every message sent before is handed over right away, so the worker
+    /// only exits once these messages were handled. With a bounded queue,
+    /// messages are handed over as the workers take them, and only the few
+    /// already handed over wait ahead of the request.
+    ///
+    /// The pool keeps at least one worker thread, or the policy's minimum when
+    /// [autoscaled](SyncArbiterBuilder::autoscale); workers already asked to
+    /// exit are not counted.
+    ///
+    /// Returns `false` if the arbiter has stopped, if only the minimum number
+    /// of workers would be left, or if the queue is bounded and full, in which
+    /// case the request can be retried later.
+    pub fn remove_worker(&self) -> bool {
+        matches!(self.workers.upgrade(), Some(workers) if workers.retire())
+    }
+
+    /// Shuts the pool down, waiting at most `timeout` for its worker threads
+    /// to exit.
+    ///
+    /// The pool stops accepting messages right away: sending to its address
+    /// fails with [`SendError::Closed`](crate::prelude::SendError::Closed),
+    /// and requests sent with [`send_to_worker`](Self::send_to_worker) or
+    /// [`send_by_key`](Self::send_by_key) fail with
+    /// [`MailboxError::Closed`]. An [autoscaled](SyncArbiterBuilder::autoscale)
+    /// pool stops scaling, and no workers can be added. The worker threads
+    /// then handle the messages still queued and exit.
+    ///
+    /// The returned future resolves to `true` if all worker threads exited
+    /// before the timeout. Threads still running after the timeout are not
+    /// interrupted, they exit once they are done with the queued messages.
+    pub fn shutdown(&self, timeout: Duration) -> ResponseFuture<bool> {
+        // the pool no longer grows or shrinks while it drains
+        if let Some(ref scaler) = self.scaler {
+            scaler.stop();
+        }
+        // messages delivered to single workers before are still handled, the
+        // workers drain their inboxes once the pool's queue is drained
+        self.threads.close();
+        // the arbiter closes its mailbox, and stops once it is drained
+        self.addr.link().stop();
+
+        let exited = Exited(Arc::clone(&self.threads));
+        Box::pin(async move { actix_rt::time::timeout(timeout, exited).await.is_ok() })
+    }
+
+    /// Sends a message to the worker thread with the given id, and
+    /// asynchronously waits for the response.
+    ///
+    /// The message skips the pool's queue and is handled by that worker once
+    /// it finishes the messages it received before. If the worker's inbox is
+    /// full, the request waits for the worker to take a message off it. The
+    /// request fails with [`MailboxError::Closed`] if there is no worker with
+    /// that id, or if the pool is [shutting down](Self::shutdown).
+    pub fn send_to_worker<M>(&self, id: usize, msg: M) -> WorkerRequest<A, M>
+    where
+        M: Message + Send + 'static,
+        M::Result: Send,
+        A: Handler<M>,
+    {
+        WorkerRequest::worker(Arc::clone(&self.threads), id, msg)
+    }
+
+    /// Sends a message to the worker thread with the given id, without
+    /// waiting for the response.
+    ///
+    /// Fails with [`SendError::Full`] if the worker's inbox is full, and with
+    /// [`SendError::Closed`] if there is no worker with that id, or if the
+    /// pool is [shutting down](Self::shutdown).
+    pub fn try_send_to_worker<M>(&self, id: usize, msg: M) -> Result<(), SendError<M>>
+    where
+        M: Message + Send + 'static,
+        M::Result: Send,
+        A: Handler<M>,
+    {
+        self.threads.deliver(id, msg, None)
+    }
+
+    /// Sends a message to the worker thread picked by the message's
+    /// [`RouteKey`], and asynchronously waits for the response.
+    ///
+    /// Messages with the same key are handled by the same worker, one after
+    /// the other, as long as no workers are added or removed; resizing the
+    /// pool moves the keys of the added or removed worker. Messages without a
+    /// key are sent to the pool's queue, like with [`Addr::send`].
+    pub fn send_by_key<M>(&self, msg: M) -> WorkerRequest<A, M>
+    where
+        M: Message + RouteKey + Send + 'static,
+        M::Result: Send,
+        A: Handler<M>,
+    {
+        match msg.route_key() {
+            Some(key) => match self.workers.upgrade().and_then(|w| w.worker_for_key(key)) {
+                Some(id) => self.send_to_worker(id, msg),
+                None => WorkerRequest::pool(Request::new(None, None)),
+            },
+            None => WorkerRequest::pool(self.addr.send(msg)),
+        }
+    }
+
+    /// Sends a message to the worker thread picked by the message's
+    /// [`RouteKey`], without waiting for the response.
+    ///
+    /// Fails with [`SendError::Full`] if the worker's inbox is full, and with
+    /// [`SendError::Closed`] if the pool is [shutting down](Self::shutdown).
+    /// Messages without a key are sent to the pool's queue, like with
+    /// [`Addr::try_send`]. See [`send_by_key`](Self::send_by_key).
+    pub fn try_send_by_key<M>(&self, msg: M) -> Result<(), SendError<M>>
+    where
+        M: Message + RouteKey + Send + 'static,
+        M::Result: Send,
+        A: Handler<M>,
+    {
+        let key = match msg.route_key() {
+            Some(key) => key,
+            None => return self.addr.try_send(msg),
+        };
+        match self.workers.upgrade().and_then(|w| w.worker_for_key(key)) {
+            Some(id) => self.threads.deliver(id, msg, None),
+            None => Err(SendError::Closed(msg)),
+        }
+    }
+
+    /// Sends a clone of the message to every worker thread, and
+    /// asynchronously waits for all responses.
+    ///
+    /// Responses are ordered by worker id. Like
+    /// [`send_to_worker`](Self::send_to_worker), the messages skip the pool's
+    /// queue.
+    pub fn broadcast<M>(&self, msg: M) -> ResponseFuture<Vec<Result<M::Result, MailboxError>>>
+    where
+        M: Message + Clone + Send + 'static,
+        M::Result: Send,
+        A: Handler<M>,
+    {
+        let ids = match self.workers.upgrade() {
+            Some(workers) => workers.threads.inboxes.lock().keys().copied().collect(),
+            None => Vec::new(),
+        };
+        let reqs: Vec<_> = ids
+            .into_iter()
+            .map(|id| self.send_to_worker(id, msg.clone()))
+            .collect();
+
+        Box::pin(async move {
+            // the messages are queued as long as the inboxes have room,
+            // awaiting them in turn only delays the workers with a full inbox
+            let mut res = Vec::with_capacity(reqs.len());
+            for req in reqs {
+                res.push(req.await);
+            }
+            res
+        })
+    }
+}
+
+/// A `Future` which represents a message sent to a single worker thread of a
+/// [`SyncArbiter`].
+///
+/// Created by [`SyncPoolHandle::send_to_worker`] and
+/// [`SyncPoolHandle::send_by_key`]. While the worker's inbox is full, the
+/// message waits in the request until the worker takes a message off it.
+#[must_use = "You have to wait on request otherwise the Message wont be delivered"]
+pub struct WorkerRequest<A, M>
+where
+    A: Actor<Context = SyncContext<A>> + Handler<M>,
+    M: Message + Send + 'static,
+    M::Result: Send,
+{
+    inner: WorkerRequestInner<A, M>,
+    timeout: Option<Pin<Box<Sleep>>>,
+}
+
+enum WorkerRequestInner<A, M>
+where
+    A: Actor<Context = SyncContext<A>> + Handler<M>,
+    M: Message + Send + 'static,
+    M::Result: Send,
+{
+    Worker {
+        threads: Arc<Threads<A>>,
+        id: usize,
+        /// The message, until it fits in the worker's inbox.
+        msg: Option<M>,
+        rx: Option<OneshotReceiver<M::Result>>,
+    },
+    /// A message without a route key, sent to the pool's queue.
+    Pool(Pin<Box<Request<A, M>>>),
+}
+
+impl<A, M> WorkerRequest<A, M>
+where
+    A: Actor<Context = SyncContext<A>> + Handler<M>,
+    M: Message + Send + 'static,
+    M::Result: Send,
+{
+    fn worker(threads: Arc<Threads<A>>, id: usize, msg: M) -> Self {
+        let (tx, rx) = oneshot_channel();
+        let (msg, rx) = match threads.deliver(id, msg, Some(tx)) {
+            Ok(()) => (None, Some(rx)),
+            Err(SendError::Full(msg)) => (Some(msg), None),
+            Err(SendError::Closed(_)) => (None, None),
+        };
+        WorkerRequest {
+            inner: WorkerRequestInner::Worker {
+                threads,
+                id,
+                msg,
+                rx,
+            },
+            timeout: None,
+        }
+    }
+
+    fn pool(req: Request<A, M>) -> Self {
+        WorkerRequest {
+            inner: WorkerRequestInner::Pool(Box::pin(req)),
+            timeout: None,
+        }
+    }
+
+    /// Set message delivery timeout
+    pub fn timeout(mut self, dur: Duration) -> Self {
+        self.timeout = Some(Box::pin(actix_rt::time::sleep(dur)));
+        self
+    }
+}
+
+// the message is never pinned
+impl<A, M> Unpin for WorkerRequest<A, M>
+where
+    A: Actor<Context = SyncContext<A>> + Handler<M>,
+    M: Message + Send + 'static,
+    M::Result: Send,
+{
+}
+
+impl<A, M> Future for WorkerRequest<A, M>
+where
+    A: Actor<Context = SyncContext<A>> + Handler<M>,
+    M: Message + Send + 'static,
+    M::Result: Send,
+{
+    type Output = Result<M::Result, MailboxError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        let res = match this.inner {
+            WorkerRequestInner::Pool(ref mut req) => req.as_mut().poll(cx),
+            WorkerRequestInner::Worker {
+                ref threads,
+                id,
+                ref mut msg,
+                ref mut rx,
+            } => {
+                let mut registered = false;
+                while let Some(m) = msg.take() {
+                    let (tx, new_rx) = oneshot_channel();
+                    match threads.deliver(id, m, Some(tx)) {
+                        Ok(()) => *rx = Some(new_rx),
+                        // retry once registered, in case the worker made room
+                        // in between
+                        Err(SendError::Full(m)) if !registered => {
+                            threads.register_sender(cx.waker());
+                            registered = true;
+                            *msg = Some(m);
+                        }
+                        Err(SendError::Full(m)) => {
+                            *msg = Some(m);
+                            break;
+                        }
+                        Err(SendError::Closed(_)) => {
+                            return Poll::Ready(Err(MailboxError::Closed))
+                        }
+                    }
+                }
+
+                match rx {
+                    Some(rx) => Pin::new(rx)
+                        .poll(cx)
+                        .map(|res| res.unwrap_or(Err(MailboxError::Closed))),
+                    None if msg.is_some() => Poll::Pending,
+                    None => Poll::Ready(Err(MailboxError::Closed)),
+                }
+            }
+        };
+
+        match res {
+            Poll::Pending => match this.timeout {
+                Some(ref mut timeout) => timeout
+                    .as_mut()
+                    .poll(cx)
+                    .map(|_| Err(MailboxError::Timeout)),
+                None => Poll::Pending,
+            },
+            res => res,
+        }
+    }
+}
+
+impl<A, M> fmt::Debug for WorkerRequest<A, M>
+where
+    A: Actor<Context = SyncContext<A>> + Handler<M>,
+    M: Message + Send + 'static,
+    M::Result: Send,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut fmt = fmt.debug_struct("WorkerRequest");
+        if let WorkerRequestInner::Worker { id, ref msg, .. } = self.inner {
+            fmt.field("worker", &id).field("queued", &msg.is_none());
+        }
+        fmt.finish()
+    }
+}
+
+impl<A> Clone for SyncPoolHandle<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    fn clone(&self) -> Self {
+        SyncPoolHandle {
+            workers: Weak::clone(&self.workers),
+            threads: Arc::clone(&self.threads),
+            addr: self.addr.clone(),
+            scaler: self.scaler.clone(),
+        }
+    }
+}
+
+impl<A> fmt::Debug for SyncPoolHandle<A>
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("SyncPoolHandle")
+            .field("workers", &self.workers())
+            .finish()
     }
 }
 
@@ -154,24 +1048,50 @@ where
 
     fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
         let this = self.get_mut();
+        if let Some(ready) = this.workers.as_ref().and_then(|w| w.ready.as_ref()) {
+            ready.register(cx.waker());
+        }
+
+        // shutting down, stop accepting messages and hand the queued ones to
+        // the workers
+        if this.msgs.take_stop_request() {
+            this.msgs.close();
+        }
+
+        let mut drained = false;
         loop {
-            match Pin::new(&mut this.msgs).poll_next(cx) {
-                Poll::Ready(Some(msg)) => {
-                    if let Some(ref queue) = this.queue {
-                        assert!(queue.send(msg).is_ok());
+            let msg = match this.pending.take() {
+                Some(msg) => msg,
+                None => match Pin::new(&mut this.msgs).poll_next(cx) {
+                    Poll::Ready(Some(msg)) => msg,
+                    Poll::Pending => break,
+                    // closed, and all messages were handed to the workers
+                    Poll::Ready(None) => {
+                        drained = true;
+                        break;
+                    }
+                },
+            };
+
+            if let Some(ref workers) = this.workers {
+                match workers.queue.try_send(msg) {
+                    Ok(()) => {}
+                    // woken once a worker takes a message
+                    Err(cb_channel::TrySendError::Full(msg)) => {
+                        this.pending = Some(msg);
+                        return Poll::Pending;
                     }
+                    Err(cb_channel::TrySendError::Disconnected(_)) => unreachable!(),
                 }
-                Poll::Pending => break,
-                Poll::Ready(None) => unreachable!(),
             }
         }
 
         // stop condition
-        if this.msgs.connected() {
+        if this.msgs.connected() && !drained {
             Poll::Pending
         } else {
             // stop sync arbiters
-            this.queue = None;
+            this.workers = None;
             Poll::Ready(())
         }
     }
@@ -183,7 +1103,7 @@ where
     M: Message + Send + 'static,
     M::Result: Send,
 {
-    fn pack(msg: M, tx: Option<SyncSender<M::Result>>) -> Envelope<A> {
+    fn pack(msg: M, tx: Option<OneshotSender<M::Result>>) -> Envelope<A> {
         Envelope::with_proxy(Box::new(SyncContextEnvelope::new(msg, tx)))
     }
 }
@@ -222,11 +1142,20 @@ where
     A: Actor<Context = SyncContext<A>>,
 {
     act: Option<A>,
+    id: usize,
     queue: cb_channel::Receiver<Envelope<A>>,
+    inbox: cb_channel::Receiver<Envelope<A>>,
+    threads: Arc<Threads<A>>,
     stopping: bool,
+    retiring: bool,
+    /// Whether a message was received, to tell a panicking actor apart from
+    /// one that cannot start.
+    received: bool,
     state: ActorState,
-    factory: Arc<dyn Fn() -> A>,
+    factory: Arc<dyn Fn(usize) -> A>,
     address: AddressSenderProducer<A>,
+    ready: Option<Arc<AtomicWaker>>,
+    timers: Timers<A>,
 }
 
 impl<A> SyncContext<A>
@@ -234,18 +1163,29 @@ where
     A: Actor<Context = Self>,
 {
     fn new(
-        factory: Arc<dyn Fn() -> A>,
+        id: usize,
+        factory: Arc<dyn Fn(usize) -> A>,
         queue: cb_channel::Receiver<Envelope<A>>,
+        inbox: cb_channel::Receiver<Envelope<A>>,
+        threads: Arc<Threads<A>>,
         address: AddressSenderProducer<A>,
+        ready: Option<Arc<AtomicWaker>>,
     ) -> Self {
-        let act = factory();
+        let act = factory(id);
         Self {
+            id,
             queue,
+            inbox,
+            threads,
             factory,
             act: Some(act),
             stopping: false,
+            retiring: false,
+            received: false,
             state: ActorState::Started,
             address,
+            ready,
+            timers: Timers::default(),
         }
     }
 
@@ -257,11 +1197,39 @@ where
         self.state = ActorState::Running;
 
         loop {
-            match self.queue.recv() {
-                Ok(mut env) => {
+            let env = match self.timers.next_deadline() {
+                // due timers run ahead of queued messages
+                Some(deadline) if deadline <= Instant::now() => None,
+                deadline => {
+                    // wait for a message or for the next timer
+                    let timer = deadline.map_or_else(cb_channel::never, cb_channel::at);
+                    cb_channel::select! {
+                        recv(self.queue) -> env => Some(env),
+                        recv(self.inbox) -> env => {
+                            // room for the requests waiting on a full inbox
+                            self.threads.wake_senders();
+                            Some(env)
+                        }
+                        recv(timer) -> _ => None,
+                    }
+                }
+            };
+            match env {
+                Some(Ok(mut env)) => {
+                    self.received = true;
+                    if let Some(ref ready) = self.ready {
+                        ready.wake();
+                    }
                     env.handle(&mut act, self);
                 }
-                Err(_) => {
+                None => self.fire_timers(&mut act),
+                Some(Err(_)) => {
+                    // the pool's queue is drained, handle the messages sent to
+                    // this worker before the pool shut down
+                    while let Ok(mut env) = self.inbox.try_recv() {
+                        env.handle(&mut act, self);
+                    }
+
                     self.state = ActorState::Stopping;
                     if A::stopping(&mut act, self) != Running::Stop {
                         warn!("stopping method is not supported for sync actors");
@@ -272,6 +1240,15 @@ where
                 }
             }
 
+            if self.retiring {
+                // the pool shrinks, stop for good
+                self.state = ActorState::Stopping;
+                A::stopping(&mut act, self);
+                self.state = ActorState::Stopped;
+                A::stopped(&mut act, self);
+                return;
+            }
+
             if self.stopping {
                 self.stopping = false;
 
@@ -280,18 +1257,107 @@ where
                 self.state = ActorState::Stopped;
                 A::stopped(&mut act, self);
 
-                // start new actor
+                // start new actor, without the old actor's timers
+                self.timers = Timers::default();
                 self.state = ActorState::Started;
-                act = (*self.factory)();
+                act = (*self.factory)(self.id);
                 A::started(&mut act, self);
                 self.state = ActorState::Running;
             }
         }
     }
 
+    /// Runs the timers that are due, rescheduling interval timers.
+    fn fire_timers(&mut self, act: &mut A) {
+        let now = Instant::now();
+        while let Some((id, timer)) = self.timers.pop_due(now) {
+            match timer {
+                Timer::Once(f) => f(act, self),
+                Timer::Interval(dur, mut f) => {
+                    self.timers.running = Some((id, false));
+                    f(act, self);
+                    // the closure may have cancelled its own timer
+                    if let Some((_, false)) = self.timers.running.take() {
+                        self.timers.insert(id, now + dur, Timer::Interval(dur, f));
+                    }
+                }
+            }
+            if self.stopping || self.retiring {
+                break;
+            }
+        }
+    }
+
     pub fn address(&self) -> Addr<A> {
         Addr::new(self.address.sender())
     }
+
+    /// Sends the message `msg` to self.
+    ///
+    /// The message is handled by this actor once it is done with the current
+    /// message, ahead of any other queued messages.
+    pub fn notify<M>(&mut self, msg: M)
+    where
+        A: Handler<M>,
+        M: Message + Send + 'static,
+        M::Result: Send,
+    {
+        self.notify_later(msg, Duration::from_secs(0));
+    }
+
+    /// Sends the message `msg` to self after a specified period of time.
+    ///
+    /// Returns a handle which can be used to cancel the notification with
+    /// [`cancel_timer`](Self::cancel_timer).
+    pub fn notify_later<M>(&mut self, msg: M, after: Duration) -> SpawnHandle
+    where
+        A: Handler<M>,
+        M: Message + Send + 'static,
+        M::Result: Send,
+    {
+        let mut env = <Self as ToEnvelope<A, M>>::pack(msg, None);
+        self.run_later(after, move |act, ctx| env.handle(act, ctx))
+    }
+
+    /// Executes a closure after a specified period of time.
+    ///
+    /// The closure runs on the worker thread, between two messages, and gets
+    /// passed the actor and its context. Returns a handle which can be used to
+    /// cancel the execution with [`cancel_timer`](Self::cancel_timer).
+    pub fn run_later<F>(&mut self, dur: Duration, f: F) -> SpawnHandle
+    where
+        F: FnOnce(&mut A, &mut Self) + 'static,
+    {
+        self.timers.schedule(dur, Timer::Once(Box::new(f)))
+    }
+
+    /// Executes a closure periodically, at a specified fixed interval.
+    ///
+    /// The first execution happens after `dur`. Returns a handle which can be
+    /// used to stop the interval with [`cancel_timer`](Self::cancel_timer).
+    pub fn run_interval<F>(&mut self, dur: Duration, f: F) -> SpawnHandle
+    where
+        F: FnMut(&mut A, &mut Self) + 'static,
+    {
+        self.timers.schedule(dur, Timer::Interval(dur, Box::new(f)))
+    }
+
+    /// Cancels a timer started with [`notify_later`](Self::notify_later),
+    /// [`run_later`](Self::run_later) or [`run_interval`](Self::run_interval).
+    ///
+    /// Returns `true` if the timer was pending.
+    pub fn cancel_timer(&mut self, handle: SpawnHandle) -> bool {
+        self.timers.cancel(handle)
+    }
+
+    /// Returns the id of the worker thread the actor runs on.
+    ///
+    /// Worker ids are unique among the running workers of a [`SyncArbiter`]
+    /// and start at 0. A worker restarted after a panic keeps its id; the id
+    /// of a removed worker is reused by the next worker added.
+    pub fn worker_id(&self) -> usize {
+        self.id
+    }
 }
 
 impl<A> ActorContext for SyncContext<A>
@@ -318,12 +1384,98 @@ where
     }
 }
 
+type OnceFn<A> = Box<dyn FnOnce(&mut A, &mut <A as Actor>::Context)>;
+type IntervalFn<A> = Box<dyn FnMut(&mut A, &mut <A as Actor>::Context)>;
+
+/// A closure scheduled on a [`SyncContext`].
+enum Timer<A: Actor> {
+    Once(OnceFn<A>),
+    Interval(Duration, IntervalFn<A>),
+}
+
+/// The timers of a [`SyncContext`], ordered by deadline.
+struct Timers<A: Actor> {
+    deadlines: BinaryHeap<Reverse<(Instant, usize)>>,
+    timers: HashMap<usize, Timer<A>>,
+    /// Interval timer whose closure is running, and whether the closure
+    /// cancelled it.
+    running: Option<(usize, bool)>,
+    handle: SpawnHandle,
+}
+
+impl<A: Actor> Default for Timers<A> {
+    fn default() -> Self {
+        Timers {
+            deadlines: BinaryHeap::new(),
+            timers: HashMap::new(),
+            running: None,
+            handle: SpawnHandle::default(),
+        }
+    }
+}
+
+impl<A: Actor> Timers<A> {
+    fn schedule(&mut self, dur: Duration, timer: Timer<A>) -> SpawnHandle {
+        self.handle = self.handle.next();
+        self.insert(self.handle.into_usize(), Instant::now() + dur, timer);
+        self.handle
+    }
+
+    fn insert(&mut self, id: usize, deadline: Instant, timer: Timer<A>) {
+        self.deadlines.push(Reverse((deadline, id)));
+        self.timers.insert(id, timer);
+    }
+
+    fn cancel(&mut self, handle: SpawnHandle) -> bool {
+        let id = handle.into_usize();
+        match self.running {
+            Some((running, ref mut cancelled)) if running == id => {
+                !std::mem::replace(cancelled, true)
+            }
+            // the deadline is skipped once it is due
+            _ => self.timers.remove(&id).is_some(),
+        }
+    }
+
+    /// Returns the deadline of the next pending timer.
+    fn next_deadline(&mut self) -> Option<Instant> {
+        while let Some(Reverse((deadline, id))) = self.deadlines.peek() {
+            if self.timers.contains_key(id) {
+                return Some(*deadline);
+            }
+            self.deadlines.pop();
+        }
+        None
+    }
+
+    /// Removes and returns the next timer due at `now`.
+    fn pop_due(&mut self, now: Instant) -> Option<(usize, Timer<A>)> {
+        if self.next_deadline()? > now {
+            return None;
+        }
+        let Reverse((_, id)) = self.deadlines.pop().unwrap();
+        self.timers.remove(&id).map(|timer| (id, timer))
+    }
+}
+
+/// Envelope asking the worker thread that receives it to exit.
+struct Retire;
+
+impl<A> EnvelopeProxy<A> for Retire
+where
+    A: Actor<Context = SyncContext<A>>,
+{
+    fn handle(&mut self, _: &mut A, ctx: &mut SyncContext<A>) {
+        ctx.retiring = true;
+    }
+}
+
 pub(crate) struct SyncContextEnvelope<M>
 where
     M: Message + Send,
 {
     msg: Option<M>,
-    tx: Option<SyncSender<M::Result>>,
+    tx: Option<OneshotSender<M::Result>>,
 }
 
 impl<M> SyncContextEnvelope<M>
@@ -331,7 +1483,7 @@ where
     M: Message + Send,
     M::Result: Send,
 {
-    pub fn new(msg: M, tx: Option<SyncSender<M::Result>>) -> Self {
+    pub fn new(msg: M, tx: Option<OneshotSender<M::Result>>) -> Self {
         Self { tx, msg: Some(msg) }
     }
 }
@@ -343,15 +1495,24 @@ where
     A: Actor<Context = SyncContext<A>> + Handler<M>,
 {
     fn handle(&mut self, act: &mut A, ctx: &mut A::Context) {
-        let tx = self.tx.take();
-        if tx.is_some() && tx.as_ref().unwrap().is_closed() {
-            return;
-        }
+        let tx = match self.tx.take() {
+            Some(tx) if M::CANCEL_ON_DROP && tx.is_closed() => return,
+            // the deadline passed while the message was queued
+            Some(tx) if tx.is_expired() => return tx.fail(MailboxError::Timeout),
+            tx => tx,
+        };
 
         if let Some(msg) = self.msg.take() {
             <A as Handler<M>>::handle(act, msg, ctx).handle(ctx, tx)
         }
     }
+
+    fn fail(&mut self, err: MailboxError) {
+        self.msg.take();
+        if let Some(tx) = self.tx.take() {
+            tx.fail(err);
+        }
+    }
 }
 
 #[cfg(test)]
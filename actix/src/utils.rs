@@ -1,4 +1,6 @@
+use std::collections::hash_map::RandomState;
 use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use std::time::Duration;
@@ -206,3 +208,11 @@ impl<A: Actor> ActorStream<A> for IntervalFunc<A> {
         }
     }
 }
+
+/// Returns a pseudo-random number.
+///
+/// This is not suitable for cryptographic purposes, but is good enough for
+/// jitter and load spreading.
+pub(crate) fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
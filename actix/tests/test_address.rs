@@ -4,6 +4,7 @@ use std::sync::Arc;
 use std::{collections::HashSet, time::Duration};
 
 use actix::prelude::*;
+use actix::{MailboxPolicy, MailboxStats};
 use actix_rt::time::sleep;
 
 #[derive(Debug)]
@@ -391,3 +392,266 @@ fn test_recipient_hash() {
         System::current().stop();
     });
 }
+
+struct Recorder(Arc<std::sync::Mutex<Vec<usize>>>);
+
+impl Actor for Recorder {
+    type Context = actix::Context<Self>;
+}
+
+struct Urgent(usize);
+
+impl Message for Urgent {
+    type Result = ();
+    const PRIORITY: Priority = Priority::High;
+}
+
+struct Lazy(usize);
+
+impl Message for Lazy {
+    type Result = ();
+    const PRIORITY: Priority = Priority::Low;
+}
+
+impl Handler<Ping> for Recorder {
+    type Result = ();
+
+    fn handle(&mut self, msg: Ping, _: &mut Self::Context) {
+        self.0.lock().unwrap().push(msg.0);
+    }
+}
+
+impl Handler<Urgent> for Recorder {
+    type Result = ();
+
+    fn handle(&mut self, msg: Urgent, _: &mut Self::Context) {
+        self.0.lock().unwrap().push(msg.0);
+    }
+}
+
+impl Handler<Lazy> for Recorder {
+    type Result = ();
+
+    fn handle(&mut self, msg: Lazy, _: &mut Self::Context) {
+        self.0.lock().unwrap().push(msg.0);
+        if msg.0 == 6 {
+            System::current().stop();
+        }
+    }
+}
+
+#[test]
+fn test_message_priority() {
+    let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let order2 = Arc::clone(&order);
+
+    let sys = System::new();
+    sys.block_on(async move {
+        let ctx = actix::Context::new();
+        let addr = ctx.address();
+
+        addr.do_send(Lazy(5));
+        addr.do_send(Ping(2));
+        addr.do_send(Urgent(0));
+        addr.do_send(Ping(3));
+        let _ = addr.try_send(Lazy(6));
+        let _ = addr.clone().recipient().do_send(Urgent(1));
+        addr.do_send(Ping(4));
+
+        ctx.run(Recorder(order2));
+    });
+    sys.run().unwrap();
+
+    assert_eq!(*order.lock().unwrap(), vec![0, 1, 2, 3, 4, 5, 6]);
+}
+
+fn bounded_recorder(policy: MailboxPolicy) -> (actix::Context<Recorder>, Addr<Recorder>) {
+    let mut ctx = actix::Context::new();
+    ctx.set_mailbox_capacity(2);
+    ctx.set_mailbox_policy(policy);
+    let addr = ctx.address();
+    (ctx, addr)
+}
+
+#[test]
+fn test_mailbox_policy() {
+    System::new().block_on(async {
+        let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        // reject
+        let (ctx, addr) = bounded_recorder(MailboxPolicy::Reject);
+        let recipient = addr.clone().recipient();
+        assert!(recipient.do_send(Ping(0)).is_ok());
+        assert!(addr.try_send(Ping(1)).is_ok());
+        assert!(matches!(
+            recipient.do_send(Ping(2)),
+            Err(SendError::Full(_))
+        ));
+        assert!(matches!(addr.try_send(Ping(3)), Err(SendError::Full(_))));
+        let rejected = addr.send(Ping(4));
+        ctx.run(Recorder(order.clone()));
+        assert!(matches!(rejected.await, Err(MailboxError::Overflow)));
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(order.lock().unwrap().drain(..).collect::<Vec<_>>(), [0, 1]);
+
+        // drop newest
+        let (ctx, addr) = bounded_recorder(MailboxPolicy::DropNewest);
+        for i in 0..4 {
+            addr.do_send(Ping(i));
+        }
+        let dropped = addr.send(Ping(4));
+        ctx.run(Recorder(order.clone()));
+        assert!(matches!(dropped.await, Err(MailboxError::Overflow)));
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(order.lock().unwrap().drain(..).collect::<Vec<_>>(), [0, 1]);
+
+        // drop oldest
+        let (ctx, addr) = bounded_recorder(MailboxPolicy::DropOldest);
+        let evicted = addr.send(Ping(0));
+        for i in 1..3 {
+            addr.do_send(Ping(i));
+        }
+        addr.do_send(Urgent(3));
+        // until the actor reads its mailbox, it holds twice its capacity
+        assert!(matches!(addr.try_send(Ping(4)), Err(SendError::Full(_))));
+        assert!(matches!(
+            addr.clone().recipient().do_send(Ping(5)),
+            Err(SendError::Full(_))
+        ));
+        let rejected = addr.send(Ping(6));
+        assert_eq!(addr.queued(), 4);
+        ctx.run(Recorder(order.clone()));
+        assert!(matches!(evicted.await, Err(MailboxError::Overflow)));
+        assert!(matches!(rejected.await, Err(MailboxError::Overflow)));
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(order.lock().unwrap().drain(..).collect::<Vec<_>>(), [3, 2]);
+
+        // unbounded
+        let (ctx, addr) = bounded_recorder(MailboxPolicy::Unbounded);
+        for i in 0..10 {
+            assert!(addr.try_send(Ping(i)).is_ok());
+        }
+        ctx.run(Recorder(order.clone()));
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(order.lock().unwrap().len(), 10);
+    });
+}
+
+struct Stats;
+
+impl Message for Stats {
+    type Result = MailboxStats;
+}
+
+impl Handler<Stats> for Recorder {
+    type Result = MessageResult<Stats>;
+
+    fn handle(&mut self, _: Stats, ctx: &mut Self::Context) -> Self::Result {
+        MessageResult(ctx.mailbox_stats())
+    }
+}
+
+#[test]
+fn test_mailbox_stats() {
+    System::new().block_on(async {
+        let ctx = actix::Context::new();
+        let addr = ctx.address();
+        let recipient = addr.clone().recipient::<Ping>();
+        assert_eq!(addr.capacity(), 16);
+
+        for i in 0..3 {
+            addr.do_send(Ping(i));
+        }
+        assert_eq!(addr.queued(), 3);
+        assert_eq!(recipient.queued(), 3);
+
+        ctx.run(Recorder(Arc::new(std::sync::Mutex::new(Vec::new()))));
+        let stats = addr.send(Stats).await.unwrap();
+        assert_eq!(
+            stats,
+            MailboxStats {
+                queued: 0,
+                peak: 4,
+                enqueued: 4,
+                dequeued: 4,
+                senders: 2,
+            }
+        );
+        assert_eq!(addr.queued(), 0);
+    });
+}
+
+#[test]
+fn test_message_deadline() {
+    System::new().block_on(async {
+        let count = Arc::new(AtomicUsize::new(0));
+        let ctx = actix::Context::new();
+        let addr = ctx.address();
+
+        tokio::time::pause();
+        let now = actix::clock::Instant::now();
+        let expired = addr.send(Ping(0)).deadline(now + Duration::from_millis(10));
+        let pending = addr
+            .clone()
+            .recipient()
+            .send(Ping(1))
+            .deadline(now + Duration::from_secs(10));
+
+        // the actor only gets to the messages after the first deadline
+        tokio::time::advance(Duration::from_millis(50)).await;
+        ctx.run(MyActor(count.clone()));
+
+        assert!(pending.await.is_ok());
+        assert!(matches!(expired.await, Err(MailboxError::Timeout)));
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    });
+}
+
+struct Lookup;
+
+impl Message for Lookup {
+    type Result = ();
+    const CANCEL_ON_DROP: bool = true;
+}
+
+struct Counter(Arc<AtomicUsize>);
+
+impl Actor for Counter {
+    type Context = Context<Self>;
+}
+
+impl Handler<Ping> for Counter {
+    type Result = ();
+
+    fn handle(&mut self, _: Ping, _: &mut Self::Context) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Handler<Lookup> for Counter {
+    type Result = ();
+
+    fn handle(&mut self, _: Lookup, _: &mut Self::Context) {
+        self.0.fetch_add(10, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn test_cancel_on_drop() {
+    System::new().block_on(async {
+        let count = Arc::new(AtomicUsize::new(0));
+        let ctx = actix::Context::new();
+        let addr = ctx.address();
+
+        drop(addr.send(Ping(0)));
+        drop(addr.send(Lookup));
+        drop(addr.clone().recipient::<Lookup>().send(Lookup));
+        let pending = addr.send(Lookup);
+
+        ctx.run(Counter(count.clone()));
+        pending.await.unwrap();
+
+        // only the message opting in to cancellation was skipped
+        assert_eq!(count.load(Ordering::Relaxed), 11);
+    });
+}
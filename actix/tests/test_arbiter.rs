@@ -52,3 +52,93 @@ fn test_start_actor_message() {
 
     assert_eq!(count.load(Ordering::Relaxed), 1);
 }
+
+struct WhichThread;
+
+impl Message for WhichThread {
+    type Result = std::thread::ThreadId;
+}
+
+struct PoolWorker;
+
+impl Actor for PoolWorker {
+    type Context = Context<Self>;
+}
+
+impl Handler<WhichThread> for PoolWorker {
+    type Result = MessageResult<WhichThread>;
+
+    fn handle(&mut self, _: WhichThread, _: &mut Self::Context) -> Self::Result {
+        // keep the instance busy, so that following messages go elsewhere
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        MessageResult(std::thread::current().id())
+    }
+}
+
+#[test]
+fn test_arbiter_pool() {
+    System::new().block_on(async {
+        let pool = ArbiterPool::start(3, || PoolWorker);
+        assert_eq!(pool.workers().len(), 3);
+
+        let reqs: Vec<_> = (0..6).map(|_| pool.send(WhichThread)).collect();
+        let mut threads = std::collections::HashSet::new();
+        for req in reqs {
+            threads.insert(req.await.unwrap());
+        }
+        assert_eq!(threads.len(), 3);
+        assert!(!threads.contains(&std::thread::current().id()));
+
+        pool.stop();
+        for addr in pool.workers() {
+            addr.closed().await;
+        }
+        assert!(!pool.connected());
+    });
+}
+
+#[test]
+fn test_arbiter_pool_drop() {
+    System::new().block_on(async {
+        let pool = ArbiterPool::start(2, || PoolWorker);
+        let workers = pool.workers();
+
+        // dropping the last clone stops the arbiters
+        drop(pool.clone());
+        assert!(workers.iter().all(|addr| addr.connected()));
+        drop(pool);
+
+        for addr in workers {
+            actix_rt::time::timeout(std::time::Duration::from_secs(1), addr.closed())
+                .await
+                .unwrap();
+        }
+    });
+}
+
+#[test]
+fn test_arbiter_pool_autoscaled() {
+    System::new().block_on(async {
+        let policy = ScalingPolicy::new(1, 3)
+            .interval(std::time::Duration::from_millis(5))
+            .queue_threshold(1)
+            .idle_timeout(std::time::Duration::from_millis(20));
+        let pool = ArbiterPool::start_autoscaled(policy, || PoolWorker);
+        assert_eq!(pool.workers().len(), 1);
+
+        // instances are added while messages are queued
+        let reqs: Vec<_> = (0..16).map(|_| pool.send(WhichThread)).collect();
+        actix_rt::time::sleep(std::time::Duration::from_millis(30)).await;
+        assert!(pool.workers().len() > 1);
+        for req in reqs {
+            req.await.unwrap();
+        }
+
+        // idle instances are removed down to the minimum
+        actix_rt::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert_eq!(pool.workers().len(), 1);
+        assert!(pool.send(WhichThread).await.is_ok());
+
+        pool.stop();
+    });
+}
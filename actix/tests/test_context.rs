@@ -557,3 +557,231 @@ mod scheduled_task_is_cancelled_properly {
         sys.run().unwrap();
     }
 }
+
+struct Stasher {
+    ready: bool,
+    handled: Vec<usize>,
+}
+
+impl Actor for Stasher {
+    type Context = Context<Self>;
+}
+
+struct Query(usize);
+
+impl Message for Query {
+    type Result = usize;
+}
+
+impl Handler<Query> for Stasher {
+    type Result = usize;
+
+    fn handle(&mut self, msg: Query, ctx: &mut Self::Context) -> usize {
+        if !self.ready {
+            ctx.stash(msg);
+            return 0;
+        }
+        self.handled.push(msg.0);
+        msg.0 * 10
+    }
+}
+
+struct Ready;
+
+impl Message for Ready {
+    type Result = usize;
+}
+
+impl Handler<Ready> for Stasher {
+    type Result = usize;
+
+    fn handle(&mut self, _: Ready, ctx: &mut Self::Context) -> usize {
+        self.ready = true;
+        let stashed = ctx.stashed();
+        ctx.unstash_all();
+        stashed
+    }
+}
+
+struct Handled;
+
+impl Message for Handled {
+    type Result = Vec<usize>;
+}
+
+impl Handler<Handled> for Stasher {
+    type Result = MessageResult<Handled>;
+
+    fn handle(&mut self, _: Handled, _: &mut Self::Context) -> Self::Result {
+        MessageResult(self.handled.clone())
+    }
+}
+
+#[actix::test]
+async fn test_stash() {
+    let addr = Stasher {
+        ready: false,
+        handled: Vec::new(),
+    }
+    .start();
+
+    let q1 = addr.send(Query(1));
+    let q2 = addr.send(Query(2));
+    let ready = addr.send(Ready);
+    let q3 = addr.send(Query(3));
+
+    assert_eq!(ready.await.unwrap(), 2);
+    assert_eq!(q1.await.unwrap(), 10);
+    assert_eq!(q2.await.unwrap(), 20);
+    assert_eq!(q3.await.unwrap(), 30);
+
+    // stashed messages are replayed ahead of the mailbox
+    assert_eq!(addr.send(Handled).await.unwrap(), vec![1, 2, 3]);
+}
+
+struct Defer(usize);
+
+impl Message for Defer {
+    type Result = ();
+}
+
+impl Handler<Defer> for Stasher {
+    type Result = ();
+
+    fn handle(&mut self, msg: Defer, ctx: &mut Self::Context) {
+        ctx.notify(Query(msg.0));
+        // not the message being handled, replayed without a response
+        ctx.stash(Query(msg.0 + 1));
+    }
+}
+
+#[actix::test]
+async fn test_stash_notify() {
+    let addr = Stasher {
+        ready: false,
+        handled: Vec::new(),
+    }
+    .start();
+
+    let q1 = addr.send(Query(1));
+    addr.send(Defer(4)).await.unwrap();
+
+    assert_eq!(addr.send(Ready).await.unwrap(), 3);
+    assert_eq!(q1.await.unwrap(), 10);
+    assert_eq!(addr.send(Handled).await.unwrap(), vec![1, 5, 4]);
+}
+
+impl Supervised for Stasher {}
+
+struct Crash;
+
+impl Message for Crash {
+    type Result = ();
+}
+
+impl Handler<Crash> for Stasher {
+    type Result = ();
+
+    fn handle(&mut self, _: Crash, ctx: &mut Self::Context) {
+        ctx.stop();
+    }
+}
+
+#[actix::test]
+async fn test_stash_restart() {
+    let addr = Supervisor::start(|_| Stasher {
+        ready: false,
+        handled: Vec::new(),
+    });
+
+    let q1 = addr.send(Query(1));
+    let q2 = addr.send(Query(2));
+    addr.do_send(Crash);
+
+    // the stash is dropped with the restarted actor
+    assert!(matches!(q1.await, Err(MailboxError::Closed)));
+    assert!(matches!(q2.await, Err(MailboxError::Closed)));
+    assert_eq!(addr.send(Ready).await.unwrap(), 0);
+    assert_eq!(addr.send(Query(3)).await.unwrap(), 30);
+    assert_eq!(addr.send(Handled).await.unwrap(), vec![3]);
+}
+
+struct Handshake;
+
+struct Hello;
+
+impl Message for Hello {
+    type Result = String;
+}
+
+struct Data;
+
+impl Message for Data {
+    type Result = String;
+}
+
+impl Actor for Handshake {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        // until the peer said hello, data is refused
+        ctx.become_(
+            Behavior::new()
+                .on(|_, _: Hello, ctx: &mut Context<Self>| {
+                    ctx.unbecome();
+                    "welcome".to_owned()
+                })
+                .on(|_, _: Data, _| "refused".to_owned()),
+        );
+    }
+}
+
+impl Handler<Hello> for Handshake {
+    type Result = String;
+
+    fn handle(&mut self, _: Hello, _: &mut Self::Context) -> Self::Result {
+        "already connected".to_owned()
+    }
+}
+
+impl Handler<Data> for Handshake {
+    type Result = String;
+
+    fn handle(&mut self, _: Data, _: &mut Self::Context) -> Self::Result {
+        "ok".to_owned()
+    }
+}
+
+#[actix::test]
+async fn test_become() {
+    let addr = Handshake.start();
+
+    assert_eq!(addr.send(Data).await.unwrap(), "refused");
+    assert_eq!(addr.send(Hello).await.unwrap(), "welcome");
+    assert_eq!(addr.send(Data).await.unwrap(), "ok");
+    assert_eq!(addr.send(Hello).await.unwrap(), "already connected");
+}
+
+struct Greet;
+
+impl Message for Greet {
+    type Result = ();
+}
+
+impl Handler<Greet> for Handshake {
+    type Result = ();
+
+    fn handle(&mut self, _: Greet, ctx: &mut Self::Context) {
+        ctx.notify(Hello);
+    }
+}
+
+#[actix::test]
+async fn test_become_notify() {
+    let addr = Handshake.start();
+
+    assert_eq!(addr.send(Data).await.unwrap(), "refused");
+    // the notified hello goes to the behavior, which removes itself
+    addr.send(Greet).await.unwrap();
+    assert_eq!(addr.send(Data).await.unwrap(), "ok");
+}
@@ -0,0 +1,330 @@
+use std::time::Duration;
+
+use actix::fsm::{Fsm, FsmActor};
+use actix::prelude::*;
+use actix_rt::time::sleep;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Link {
+    Idle,
+    Connecting,
+    Connected,
+}
+
+struct Connect;
+
+impl Message for Connect {
+    type Result = bool;
+}
+
+struct Established;
+
+impl Message for Established {
+    type Result = ();
+}
+
+struct History;
+
+impl Message for History {
+    type Result = Vec<(Link, Link, bool)>;
+}
+
+struct Hooks;
+
+impl Message for Hooks {
+    type Result = Vec<String>;
+}
+
+struct Status;
+
+impl Message for Status {
+    type Result = String;
+}
+
+struct Connection {
+    fsm: Fsm<Link>,
+    hooks: Vec<String>,
+}
+
+impl Actor for Connection {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.enter_initial(ctx);
+    }
+}
+
+impl FsmActor for Connection {
+    type State = Link;
+
+    fn fsm(&self) -> &Fsm<Link> {
+        &self.fsm
+    }
+
+    fn fsm_mut(&mut self) -> &mut Fsm<Link> {
+        &mut self.fsm
+    }
+
+    fn on_enter(&mut self, state: &Link, _: &mut Context<Self>) {
+        self.hooks.push(format!("enter {:?}", state));
+    }
+
+    fn on_exit(&mut self, state: &Link, _: &mut Context<Self>) {
+        self.hooks.push(format!("exit {:?}", state));
+    }
+
+    fn state_timeout(&self, state: &Link) -> Option<Duration> {
+        match state {
+            Link::Connecting => Some(Duration::from_millis(50)),
+            _ => None,
+        }
+    }
+
+    fn on_state_timeout(&mut self, _: &Link, ctx: &mut Context<Self>) {
+        self.transition(Link::Idle, ctx);
+    }
+
+    fn state_behavior(&self, state: &Link) -> Option<Behavior<Self>> {
+        match state {
+            Link::Idle => None,
+            Link::Connecting => {
+                Some(Behavior::new().on(|_, _: Status, _| "connecting".to_owned()))
+            }
+            Link::Connected => {
+                Some(Behavior::new().on(|_, _: Status, _| "connected".to_owned()))
+            }
+        }
+    }
+}
+
+impl Handler<Connect> for Connection {
+    type Result = bool;
+
+    fn handle(&mut self, _: Connect, ctx: &mut Context<Self>) -> bool {
+        match self.state() {
+            Link::Idle => {
+                self.transition(Link::Connecting, ctx);
+                true
+            }
+            Link::Connecting | Link::Connected => false,
+        }
+    }
+}
+
+impl Handler<Established> for Connection {
+    type Result = ();
+
+    fn handle(&mut self, _: Established, ctx: &mut Context<Self>) {
+        if *self.state() == Link::Connecting {
+            self.transition(Link::Connected, ctx);
+        }
+    }
+}
+
+impl Handler<Status> for Connection {
+    type Result = String;
+
+    fn handle(&mut self, _: Status, _: &mut Context<Self>) -> String {
+        "idle".to_owned()
+    }
+}
+
+impl Handler<History> for Connection {
+    type Result = MessageResult<History>;
+
+    fn handle(&mut self, _: History, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(
+            self.fsm
+                .transitions()
+                .map(|t| (t.from.clone(), t.to.clone(), t.timed_out))
+                .collect(),
+        )
+    }
+}
+
+impl Handler<Hooks> for Connection {
+    type Result = MessageResult<Hooks>;
+
+    fn handle(&mut self, _: Hooks, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(self.hooks.clone())
+    }
+}
+
+#[test]
+fn test_fsm_transitions() {
+    System::new().block_on(async {
+        let addr = Connection {
+            fsm: Fsm::new(Link::Idle),
+            hooks: Vec::new(),
+        }
+        .start();
+
+        assert!(addr.send(Connect).await.unwrap());
+        assert!(!addr.send(Connect).await.unwrap());
+        addr.send(Established).await.unwrap();
+
+        // connected is not timed out
+        sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(
+            addr.send(History).await.unwrap(),
+            vec![
+                (Link::Idle, Link::Connecting, false),
+                (Link::Connecting, Link::Connected, false),
+            ]
+        );
+        assert_eq!(
+            addr.send(Hooks).await.unwrap(),
+            vec![
+                "enter Idle",
+                "exit Idle",
+                "enter Connecting",
+                "exit Connecting",
+                "enter Connected",
+            ]
+        );
+    });
+}
+
+#[test]
+fn test_fsm_state_timeout() {
+    System::new().block_on(async {
+        let addr = Connection {
+            fsm: Fsm::with_log_capacity(Link::Idle, 2),
+            hooks: Vec::new(),
+        }
+        .start();
+
+        assert!(addr.send(Connect).await.unwrap());
+        sleep(Duration::from_millis(100)).await;
+        assert!(addr.send(Connect).await.unwrap());
+
+        // the oldest transition was dropped from the log
+        assert_eq!(
+            addr.send(History).await.unwrap(),
+            vec![
+                (Link::Connecting, Link::Idle, true),
+                (Link::Idle, Link::Connecting, false),
+            ]
+        );
+    });
+}
+
+#[test]
+fn test_fsm_state_behavior() {
+    System::new().block_on(async {
+        let addr = Connection {
+            fsm: Fsm::new(Link::Idle),
+            hooks: Vec::new(),
+        }
+        .start();
+
+        assert_eq!(addr.send(Status).await.unwrap(), "idle");
+        assert!(addr.send(Connect).await.unwrap());
+        assert_eq!(addr.send(Status).await.unwrap(), "connecting");
+
+        // leaving the state removes its behavior
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(addr.send(Status).await.unwrap(), "idle");
+
+        assert!(addr.send(Connect).await.unwrap());
+        addr.send(Established).await.unwrap();
+        assert_eq!(addr.send(Status).await.unwrap(), "connected");
+    });
+}
+
+struct Flaky {
+    fsm: Fsm<Link>,
+    restarted: bool,
+}
+
+impl Actor for Flaky {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.set_catch_unwind(true);
+        self.enter_initial(ctx);
+    }
+}
+
+impl Supervised for Flaky {
+    fn restarting(&mut self, _: &mut Context<Self>) {
+        self.restarted = true;
+    }
+}
+
+impl FsmActor for Flaky {
+    type State = Link;
+
+    fn fsm(&self) -> &Fsm<Link> {
+        &self.fsm
+    }
+
+    fn fsm_mut(&mut self) -> &mut Fsm<Link> {
+        &mut self.fsm
+    }
+
+    fn state_timeout(&self, state: &Link) -> Option<Duration> {
+        match state {
+            Link::Connecting if !self.restarted => Some(Duration::from_millis(10)),
+            _ => None,
+        }
+    }
+
+    fn on_state_timeout(&mut self, _: &Link, _: &mut Context<Self>) {
+        panic!("timeout panic");
+    }
+}
+
+impl Handler<Connect> for Flaky {
+    type Result = bool;
+
+    fn handle(&mut self, _: Connect, ctx: &mut Context<Self>) -> bool {
+        self.transition(Link::Connecting, ctx);
+        true
+    }
+}
+
+impl Handler<Established> for Flaky {
+    type Result = ();
+
+    fn handle(&mut self, _: Established, ctx: &mut Context<Self>) {
+        self.transition(Link::Connected, ctx);
+    }
+}
+
+impl Handler<History> for Flaky {
+    type Result = MessageResult<History>;
+
+    fn handle(&mut self, _: History, _: &mut Context<Self>) -> Self::Result {
+        MessageResult(
+            self.fsm
+                .transitions()
+                .map(|t| (t.from.clone(), t.to.clone(), t.timed_out))
+                .collect(),
+        )
+    }
+}
+
+#[test]
+fn test_fsm_restart() {
+    System::new().block_on(async {
+        let addr = Supervisor::start(|_| Flaky {
+            fsm: Fsm::new(Link::Idle),
+            restarted: false,
+        });
+
+        // the timeout handler panics, and the actor restarts in its state
+        assert!(addr.send(Connect).await.unwrap());
+        sleep(Duration::from_millis(50)).await;
+        addr.send(Established).await.unwrap();
+
+        assert_eq!(
+            addr.send(History).await.unwrap(),
+            vec![
+                (Link::Idle, Link::Connecting, false),
+                (Link::Connecting, Link::Connected, false),
+            ]
+        );
+    });
+}
@@ -277,3 +277,232 @@ fn test_stop_restore_after_stopping() {
     assert!(stopping.load(Ordering::Relaxed), "Not stopping");
     assert!(!stopped.load(Ordering::Relaxed), "Stopped");
 }
+
+struct Child {
+    name: &'static str,
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+impl Actor for Child {
+    type Context = actix::Context<Self>;
+
+    fn stopping(&mut self, _: &mut Self::Context) -> Running {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("{} stopping", self.name));
+        Running::Stop
+    }
+
+    fn stopped(&mut self, _: &mut Self::Context) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("{} stopped", self.name));
+        if self.name == "b" {
+            System::current().stop();
+        }
+    }
+}
+
+struct Quit;
+
+impl Message for Quit {
+    type Result = ();
+}
+
+impl Handler<Quit> for Child {
+    type Result = ();
+
+    fn handle(&mut self, _: Quit, ctx: &mut Self::Context) {
+        ctx.stop();
+    }
+}
+
+struct Parent {
+    events: Arc<Mutex<Vec<String>>>,
+    a: Option<Addr<Child>>,
+    b: Option<Addr<Child>>,
+}
+
+impl Actor for Parent {
+    type Context = actix::Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let events = self.events.clone();
+        let a = ctx.spawn_child(|_| Child { name: "a", events });
+        let events = self.events.clone();
+        let b = ctx.spawn_child(|_| Child { name: "b", events });
+
+        a.do_send(Quit);
+        self.a = Some(a);
+        self.b = Some(b);
+    }
+
+    fn stopped(&mut self, _: &mut Self::Context) {
+        self.events
+            .lock()
+            .unwrap()
+            .push("parent stopped".to_owned());
+    }
+}
+
+impl Handler<ChildTerminated> for Parent {
+    type Result = ();
+
+    fn handle(&mut self, msg: ChildTerminated, ctx: &mut Self::Context) {
+        assert_eq!(Some(msg.id), self.a.as_ref().map(Addr::id));
+        self.events.lock().unwrap().push("a terminated".to_owned());
+
+        // children are stopped even though we hold their addresses
+        ctx.stop();
+    }
+}
+
+#[test]
+fn test_spawn_child() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let events1 = events.clone();
+
+    let sys = System::new();
+    let _addr = sys.block_on(async move {
+        Parent {
+            events: events1,
+            a: None,
+            b: None,
+        }
+        .start()
+    });
+    sys.run().unwrap();
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            "a stopping",
+            "a stopped",
+            "a terminated",
+            "parent stopped",
+            "b stopping",
+            "b stopped",
+        ]
+    );
+}
+
+struct Watcher {
+    watched: Addr<Child>,
+    terminated: Arc<Mutex<Vec<ActorId>>>,
+}
+
+impl Actor for Watcher {
+    type Context = actix::Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.watch(&self.watched);
+        self.watched.do_send(Quit);
+    }
+}
+
+impl Handler<Terminated> for Watcher {
+    type Result = ();
+
+    fn handle(&mut self, msg: Terminated, ctx: &mut Self::Context) {
+        let mut terminated = self.terminated.lock().unwrap();
+        terminated.push(msg.id);
+
+        if terminated.len() == 1 {
+            // watching an actor that has already stopped notifies right away
+            ctx.watch(&self.watched);
+        } else {
+            System::current().stop();
+        }
+    }
+}
+
+#[test]
+fn test_watch() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let terminated = Arc::new(Mutex::new(Vec::new()));
+    let terminated1 = terminated.clone();
+
+    let sys = System::new();
+    let (_addr, id) = sys.block_on(async move {
+        let watched = Child { name: "a", events }.start();
+        let id = watched.id();
+        let addr = Watcher {
+            watched,
+            terminated: terminated1,
+        }
+        .start();
+        (addr, id)
+    });
+    sys.run().unwrap();
+
+    assert_eq!(*terminated.lock().unwrap(), vec![id, id]);
+}
+
+struct Exiting;
+
+impl Actor for Exiting {
+    type Context = actix::Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.set_catch_unwind(true);
+    }
+}
+
+#[derive(Clone)]
+enum Exit {
+    Stop,
+    StopWithReason,
+    Terminate,
+    Panic,
+}
+
+impl Message for Exit {
+    type Result = ();
+}
+
+impl Handler<Exit> for Exiting {
+    type Result = ();
+
+    fn handle(&mut self, msg: Exit, ctx: &mut Self::Context) {
+        match msg {
+            Exit::Stop => ctx.stop(),
+            Exit::StopWithReason => ctx.stop_with_reason("done"),
+            Exit::Terminate => ctx.terminate(),
+            Exit::Panic => panic!("exit"),
+        }
+    }
+}
+
+#[test]
+fn test_exit_reason() {
+    System::new().block_on(async {
+        let (addr, handle) = Exiting.start_with_handle();
+        drop(addr);
+        assert_eq!(handle.await, ExitReason::Normal);
+
+        for (msg, reason) in [
+            (Exit::Stop, ExitReason::Stopped),
+            (Exit::StopWithReason, ExitReason::Custom("done".to_owned())),
+            (Exit::Terminate, ExitReason::Terminated),
+            (Exit::Panic, ExitReason::Panicked),
+        ]
+        .iter()
+        .cloned()
+        {
+            let (addr, handle) = Exiting.start_with_handle();
+            let weak = addr.downgrade();
+            addr.do_send(msg);
+
+            addr.closed().await;
+            assert!(!addr.connected());
+            assert_eq!(handle.await, reason);
+
+            // already stopped
+            addr.closed().await;
+            drop(addr);
+            weak.closed().await;
+        }
+    });
+}
@@ -0,0 +1,188 @@
+use std::collections::HashSet;
+
+use actix::actors::router::{
+    AddRoutee, Broadcast, RecipientRouter, RemoveRoutee, RouteKey, Router, Strategy,
+};
+use actix::prelude::*;
+
+#[derive(Clone)]
+struct Which(Option<u64>);
+
+impl Message for Which {
+    type Result = usize;
+}
+
+impl RouteKey for Which {
+    fn route_key(&self) -> Option<u64> {
+        self.0
+    }
+}
+
+struct Stop;
+
+impl Message for Stop {
+    type Result = ();
+}
+
+struct Worker(usize);
+
+impl Actor for Worker {
+    type Context = Context<Self>;
+}
+
+impl Handler<Which> for Worker {
+    type Result = usize;
+
+    fn handle(&mut self, _: Which, _: &mut Self::Context) -> usize {
+        self.0
+    }
+}
+
+impl Handler<Stop> for Worker {
+    type Result = ();
+
+    fn handle(&mut self, _: Stop, ctx: &mut Self::Context) {
+        ctx.stop();
+    }
+}
+
+fn workers(n: usize) -> Vec<Addr<Worker>> {
+    (0..n).map(|i| Worker(i).start()).collect()
+}
+
+#[test]
+fn test_round_robin() {
+    System::new().block_on(async {
+        let router = Router::new(Strategy::RoundRobin)
+            .routees(workers(3))
+            .start();
+
+        let mut order = Vec::new();
+        for _ in 0..6 {
+            order.push(router.send(Which(None)).await.unwrap());
+        }
+        assert_eq!(order, [0, 1, 2, 0, 1, 2]);
+    });
+}
+
+#[test]
+fn test_random() {
+    System::new().block_on(async {
+        let router = Router::new(Strategy::Random).routees(workers(3)).start();
+
+        let mut seen = HashSet::new();
+        for _ in 0..100 {
+            seen.insert(router.send(Which(None)).await.unwrap());
+        }
+        assert_eq!(seen.len(), 3);
+    });
+}
+
+#[test]
+fn test_consistent_hash() {
+    System::new().block_on(async {
+        let addrs = workers(4);
+        let router = Router::new(Strategy::ConsistentHash)
+            .routees(addrs.clone())
+            .start();
+
+        let mut routes = Vec::new();
+        for key in 0..32 {
+            let idx = router.send(Which(Some(key))).await.unwrap();
+            assert_eq!(router.send(Which(Some(key))).await.unwrap(), idx);
+            routes.push(idx);
+        }
+
+        // only the keys of the removed routee move
+        assert!(router.send(RemoveRoutee(addrs[0].clone())).await.unwrap());
+        for key in 0..32 {
+            let idx = router.send(Which(Some(key))).await.unwrap();
+            assert_ne!(idx, 0);
+            if routes[key as usize] != 0 {
+                assert_eq!(idx, routes[key as usize]);
+            }
+        }
+    });
+}
+
+#[test]
+fn test_smallest_mailbox() {
+    System::new().block_on(async {
+        // a routee that is not running yet, its mailbox fills up
+        let ctx = Context::new();
+        let busy = ctx.address();
+        busy.do_send(Which(None));
+
+        let router = Router::new(Strategy::SmallestMailbox)
+            .routees(vec![busy, Worker(1).start()])
+            .start();
+
+        for _ in 0..4 {
+            assert_eq!(router.send(Which(None)).await.unwrap(), 1);
+        }
+        ctx.run(Worker(0));
+    });
+}
+
+#[test]
+fn test_routees_at_runtime() {
+    System::new().block_on(async {
+        let router = Router::new(Strategy::RoundRobin).start();
+        assert!(matches!(
+            router.send(Which(None)).await,
+            Err(MailboxError::Closed)
+        ));
+
+        let addrs = workers(2);
+        router.send(AddRoutee(addrs[1].clone())).await.unwrap();
+        assert_eq!(router.send(Which(None)).await.unwrap(), 1);
+
+        router.send(AddRoutee(addrs[0].clone())).await.unwrap();
+        assert!(router.send(RemoveRoutee(addrs[1].clone())).await.unwrap());
+        assert!(!router.send(RemoveRoutee(addrs[1].clone())).await.unwrap());
+        assert_eq!(router.send(Which(None)).await.unwrap(), 0);
+
+        // stopped routees are dropped from the pool
+        router.send(AddRoutee(addrs[1].clone())).await.unwrap();
+        addrs[0].send(Stop).await.unwrap();
+        addrs[0].clone().closed().await;
+        for _ in 0..3 {
+            assert_eq!(router.send(Which(None)).await.unwrap(), 1);
+        }
+    });
+}
+
+#[test]
+fn test_broadcast() {
+    System::new().block_on(async {
+        let router = Router::new(Strategy::Random).routees(workers(3)).start();
+
+        let res = router.send(Broadcast(Which(None))).await.unwrap();
+        assert_eq!(
+            res.into_iter().map(Result::unwrap).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+    });
+}
+
+#[test]
+fn test_recipient_router() {
+    System::new().block_on(async {
+        let recipients = workers(2).into_iter().map(Addr::recipient::<Which>);
+        let router = RecipientRouter::new(Strategy::RoundRobin)
+            .routees(recipients)
+            .start();
+
+        assert_eq!(router.send(Which(None)).await.unwrap(), 0);
+        assert_eq!(router.send(Which(None)).await.unwrap(), 1);
+
+        let res = router.send(Broadcast(Which(None))).await.unwrap();
+        assert_eq!(res.len(), 2);
+
+        let extra = Worker(2).start().recipient();
+        router.send(AddRoutee(extra.clone())).await.unwrap();
+        assert_eq!(router.send(Which(None)).await.unwrap(), 2);
+        assert_eq!(router.send(Which(None)).await.unwrap(), 0);
+        assert!(router.send(RemoveRoutee(extra)).await.unwrap());
+    });
+}
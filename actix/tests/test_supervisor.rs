@@ -5,6 +5,7 @@ use std::{
 };
 
 use actix::prelude::*;
+use actix::{RestartPolicy, SupervisorGroup, SupervisorStrategy};
 use actix_rt::time::sleep;
 
 struct Die;
@@ -68,3 +69,392 @@ fn test_supervisor_restart() {
     assert_eq!(restarts.load(Ordering::Relaxed), 2);
     assert_eq!(messages.load(Ordering::Relaxed), 2);
 }
+
+struct Panic;
+
+impl Message for Panic {
+    type Result = ();
+}
+
+struct Ping;
+
+impl Message for Ping {
+    type Result = usize;
+}
+
+struct PanicActor(Arc<AtomicUsize>);
+
+impl Actor for PanicActor {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        ctx.set_catch_unwind(true);
+    }
+}
+
+impl actix::Supervised for PanicActor {
+    fn restarting(&mut self, _: &mut Context<Self>) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Handler<Panic> for PanicActor {
+    type Result = ();
+
+    fn handle(&mut self, _: Panic, _: &mut Context<Self>) {
+        panic!("handler panic");
+    }
+}
+
+impl Handler<Ping> for PanicActor {
+    type Result = usize;
+
+    fn handle(&mut self, _: Ping, _: &mut Context<Self>) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[test]
+fn test_supervisor_restart_after_panic() {
+    let restarts = Arc::new(AtomicUsize::new(0));
+    let restarts2 = Arc::clone(&restarts);
+
+    let sys = System::new();
+    sys.block_on(async move {
+        let addr = actix::Supervisor::start(move |_| PanicActor(restarts2));
+
+        let res = addr.send(Panic).await;
+        assert!(matches!(res, Err(MailboxError::Panicked)));
+
+        // the actor got restarted and keeps handling messages
+        assert_eq!(addr.send(Ping).await.unwrap(), 1);
+    });
+
+    assert_eq!(restarts.load(Ordering::Relaxed), 1);
+}
+
+struct HookPanic(Arc<AtomicUsize>);
+
+impl Actor for HookPanic {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        ctx.set_catch_unwind(true);
+        if self.0.fetch_add(1, Ordering::Relaxed) == 0 {
+            panic!("started panic");
+        }
+    }
+
+    fn stopped(&mut self, _: &mut Context<Self>) {
+        panic!("stopped panic");
+    }
+}
+
+impl actix::Supervised for HookPanic {}
+
+impl Handler<Ping> for HookPanic {
+    type Result = usize;
+
+    fn handle(&mut self, _: Ping, _: &mut Context<Self>) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Handler<Die> for HookPanic {
+    type Result = ();
+
+    fn handle(&mut self, _: Die, ctx: &mut Context<Self>) {
+        ctx.stop();
+    }
+}
+
+#[test]
+fn test_catch_unwind_in_hooks() {
+    System::new().block_on(async {
+        // a panic in `started` restarts a supervised actor
+        let starts = Arc::new(AtomicUsize::new(0));
+        let starts2 = Arc::clone(&starts);
+        let addr = actix::Supervisor::start(move |_| HookPanic(starts2));
+        assert_eq!(addr.send(Ping).await.unwrap(), 2);
+
+        // a panic in `stopped` is reported as the exit reason
+        let (addr, handle) = HookPanic(Arc::new(AtomicUsize::new(1))).start_with_handle();
+        addr.send(Die).await.unwrap();
+        assert_eq!(handle.await, ExitReason::Panicked);
+    });
+}
+
+struct CrashLoop(Arc<AtomicUsize>);
+
+impl Actor for CrashLoop {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        ctx.stop();
+    }
+}
+
+impl actix::Supervised for CrashLoop {}
+
+#[test]
+fn test_supervisor_restart_policy() {
+    let starts = Arc::new(AtomicUsize::new(0));
+    let starts2 = Arc::clone(&starts);
+
+    let sys = System::new();
+    sys.block_on(async move {
+        tokio::time::pause();
+
+        let policy = RestartPolicy::new()
+            .max_restarts(3, Duration::from_secs(60))
+            .backoff(Duration::from_millis(40), Duration::from_secs(1));
+        let addr = actix::Supervisor::start_with_policy(policy, move |_| CrashLoop(starts2));
+        advance(Duration::default()).await;
+        assert_eq!(starts.load(Ordering::Relaxed), 1);
+
+        // restarts are delayed by the backoff, doubled after every restart
+        // and randomized down to half: 40ms, 80ms and 160ms
+        for (delay, restarted) in [(40, 2), (80, 3), (160, 4)] {
+            // timers fire on the next millisecond
+            let mut elapsed = 0;
+            while starts.load(Ordering::Relaxed) < restarted {
+                assert!(addr.connected());
+                assert!(elapsed <= delay, "no restart after {}ms", elapsed);
+                advance(Duration::from_millis(1)).await;
+                elapsed += 1;
+            }
+            assert!(elapsed >= delay / 2, "restarted after {}ms", elapsed);
+        }
+
+        // the budget is exhausted
+        assert!(!addr.connected());
+        advance(Duration::from_secs(1)).await;
+        assert_eq!(starts.load(Ordering::Relaxed), 4);
+    });
+}
+
+/// Advances the paused clock, and lets the woken actors run.
+async fn advance(dur: Duration) {
+    tokio::time::advance(dur).await;
+    for _ in 0..10 {
+        tokio::task::yield_now().await;
+    }
+}
+
+struct Inc;
+
+impl Message for Inc {
+    type Result = usize;
+}
+
+struct Counter(usize);
+
+impl Actor for Counter {
+    type Context = Context<Self>;
+}
+
+impl actix::Supervised for Counter {}
+
+impl Handler<Inc> for Counter {
+    type Result = usize;
+
+    fn handle(&mut self, _: Inc, _: &mut Context<Self>) -> usize {
+        self.0 += 1;
+        self.0
+    }
+}
+
+impl Handler<Die> for Counter {
+    type Result = ();
+
+    fn handle(&mut self, _: Die, ctx: &mut Context<Self>) {
+        ctx.stop();
+    }
+}
+
+#[test]
+fn test_supervisor_restart_with_factory() {
+    let created = Arc::new(AtomicUsize::new(0));
+    let created2 = Arc::clone(&created);
+
+    let sys = System::new();
+    sys.block_on(async move {
+        let addr = actix::Supervisor::start_with_factory(RestartPolicy::default(), move |_| {
+            created2.fetch_add(1, Ordering::Relaxed);
+            Counter(0)
+        });
+
+        assert_eq!(addr.send(Inc).await.unwrap(), 1);
+        assert_eq!(addr.send(Inc).await.unwrap(), 2);
+
+        // the restarted actor does not keep the state of the failed one
+        addr.send(Die).await.unwrap();
+        assert_eq!(addr.send(Inc).await.unwrap(), 1);
+    });
+
+    assert_eq!(created.load(Ordering::Relaxed), 2);
+}
+
+#[derive(Default)]
+struct Lifecycle {
+    started: AtomicUsize,
+    stopped: AtomicUsize,
+    restarts: AtomicUsize,
+}
+
+struct Worker(Arc<Lifecycle>);
+
+impl Actor for Worker {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _: &mut Context<Self>) {
+        self.0.started.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn stopped(&mut self, _: &mut Context<Self>) {
+        self.0.stopped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl actix::Supervised for Worker {
+    fn restarting(&mut self, _: &mut Context<Self>) {
+        self.0.restarts.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Handler<Die> for Worker {
+    type Result = ();
+
+    fn handle(&mut self, _: Die, ctx: &mut Context<Self>) {
+        ctx.stop();
+    }
+}
+
+fn restarts(counters: &[Arc<Lifecycle>]) -> Vec<usize> {
+    counters
+        .iter()
+        .map(|c| c.restarts.load(Ordering::Relaxed))
+        .collect()
+}
+
+fn lifecycles(counters: &[Arc<Lifecycle>]) -> Vec<(usize, usize)> {
+    counters
+        .iter()
+        .map(|c| {
+            (
+                c.started.load(Ordering::Relaxed),
+                c.stopped.load(Ordering::Relaxed),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_supervisor_group_strategies() {
+    let sys = System::new();
+    sys.block_on(async {
+        let counters: Vec<_> = (0..3).map(|_| Arc::default()).collect();
+
+        let mut group =
+            SupervisorGroup::new(SupervisorStrategy::OneForOne, RestartPolicy::new());
+        let addrs: Vec<_> = counters
+            .iter()
+            .map(|c| {
+                let c = Arc::clone(c);
+                group.child(move |_| Worker(c))
+            })
+            .collect();
+        group.start();
+
+        addrs[1].send(Die).await.unwrap();
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(restarts(&counters), vec![0, 1, 0]);
+        assert_eq!(lifecycles(&counters), vec![(1, 0), (2, 1), (1, 0)]);
+
+        let counters: Vec<_> = (0..3).map(|_| Arc::default()).collect();
+
+        let mut group =
+            SupervisorGroup::new(SupervisorStrategy::OneForAll, RestartPolicy::new());
+        let addrs: Vec<_> = counters
+            .iter()
+            .map(|c| {
+                let c = Arc::clone(c);
+                group.child(move |_| Worker(c))
+            })
+            .collect();
+        group.start();
+
+        addrs[1].send(Die).await.unwrap();
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(restarts(&counters), vec![1, 1, 1]);
+        // terminated siblings stop once and start once more
+        assert_eq!(lifecycles(&counters), vec![(2, 1), (2, 1), (2, 1)]);
+
+        let counters: Vec<_> = (0..3).map(|_| Arc::default()).collect();
+
+        let mut group =
+            SupervisorGroup::new(SupervisorStrategy::RestForOne, RestartPolicy::new());
+        let addrs: Vec<_> = counters
+            .iter()
+            .map(|c| {
+                let c = Arc::clone(c);
+                group.child(move |_| Worker(c))
+            })
+            .collect();
+        group.start();
+
+        addrs[1].send(Die).await.unwrap();
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(restarts(&counters), vec![0, 1, 1]);
+        assert_eq!(lifecycles(&counters), vec![(1, 0), (2, 1), (2, 1)]);
+
+        // addresses stay valid across restarts
+        for addr in addrs {
+            assert!(addr.connected());
+        }
+    });
+}
+
+#[test]
+fn test_supervisor_group_escalation() {
+    let sys = System::new();
+    sys.block_on(async {
+        let inner_restarts = Arc::new(Lifecycle::default());
+        let outer_restarts = Arc::new(Lifecycle::default());
+
+        let mut inner = SupervisorGroup::new(
+            SupervisorStrategy::OneForOne,
+            RestartPolicy::new().max_restarts(1, Duration::from_secs(60)),
+        );
+        let c = Arc::clone(&inner_restarts);
+        let inner_addr = inner.child(move |_| Worker(c));
+
+        let mut outer = SupervisorGroup::new(
+            SupervisorStrategy::OneForAll,
+            RestartPolicy::new().max_restarts(1, Duration::from_secs(60)),
+        );
+        let c = Arc::clone(&outer_restarts);
+        let outer_addr = outer.child(move |_| Worker(c));
+        outer.group(inner);
+        outer.start();
+
+        // handled by the inner group
+        inner_addr.send(Die).await.unwrap();
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(inner_restarts.restarts.load(Ordering::Relaxed), 1);
+        assert_eq!(outer_restarts.restarts.load(Ordering::Relaxed), 0);
+
+        // the inner group gives up and the outer group restarts everything
+        inner_addr.send(Die).await.unwrap();
+        sleep(Duration::from_millis(10)).await;
+        assert_eq!(inner_restarts.restarts.load(Ordering::Relaxed), 2);
+        assert_eq!(outer_restarts.restarts.load(Ordering::Relaxed), 1);
+
+        // the outer group exhausted its budget as well and stops all children
+        outer_addr.send(Die).await.unwrap();
+        sleep(Duration::from_millis(10)).await;
+        assert!(!outer_addr.connected());
+        assert!(!inner_addr.connected());
+    });
+}
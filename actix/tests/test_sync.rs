@@ -98,3 +98,559 @@ fn test_sync() {
         "Wrong number of messages"
     );
 }
+
+struct Slow;
+
+impl Message for Slow {
+    type Result = std::thread::ThreadId;
+}
+
+struct SlowActor {
+    stopped: Arc<AtomicUsize>,
+}
+
+impl Actor for SlowActor {
+    type Context = SyncContext<Self>;
+
+    fn stopped(&mut self, _: &mut Self::Context) {
+        self.stopped.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl Handler<Slow> for SlowActor {
+    type Result = MessageResult<Slow>;
+
+    fn handle(&mut self, _: Slow, _: &mut Self::Context) -> Self::Result {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        MessageResult(std::thread::current().id())
+    }
+}
+
+#[test]
+fn test_sync_autoscaled() {
+    System::new().block_on(async {
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let policy = ScalingPolicy::new(1, 3)
+            .interval(std::time::Duration::from_millis(5))
+            .queue_threshold(1)
+            .idle_timeout(std::time::Duration::from_millis(20));
+        let counter = Arc::clone(&stopped);
+        let addr = SyncArbiter::start_autoscaled(policy, move || SlowActor {
+            stopped: Arc::clone(&counter),
+        });
+
+        let reqs: Vec<_> = (0..30).map(|_| addr.send(Slow)).collect();
+        let mut threads = std::collections::HashSet::new();
+        for req in reqs {
+            threads.insert(req.await.unwrap());
+        }
+        assert!(threads.len() > 1);
+        assert!(threads.len() <= 3);
+
+        // idle threads are removed down to the minimum
+        actix_rt::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(stopped.load(Ordering::SeqCst) >= threads.len() - 1);
+        assert!(addr.send(Slow).await.is_ok());
+    });
+}
+
+struct Explode;
+
+impl Message for Explode {
+    type Result = ();
+}
+
+impl Handler<Explode> for SlowActor {
+    type Result = ();
+
+    fn handle(&mut self, _: Explode, _: &mut Self::Context) {
+        panic!("explode");
+    }
+}
+
+#[test]
+fn test_sync_panic() {
+    System::new().block_on(async {
+        let started = Arc::new(AtomicUsize::new(0));
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let (s1, s2) = (Arc::clone(&started), Arc::clone(&stopped));
+        let addr = SyncArbiter::start(1, move || {
+            s1.fetch_add(1, Ordering::SeqCst);
+            SlowActor {
+                stopped: Arc::clone(&s2),
+            }
+        });
+
+        let before = addr.send(Slow).await.unwrap();
+        assert!(matches!(
+            addr.send(Explode).await,
+            Err(MailboxError::Panicked)
+        ));
+
+        // the actor was replaced on a new thread
+        let after = addr.send(Slow).await.unwrap();
+        assert_ne!(before, after);
+        assert_eq!(started.load(Ordering::SeqCst), 2);
+        assert_eq!(stopped.load(Ordering::SeqCst), 0);
+    });
+}
+
+#[test]
+fn test_sync_panic_on_start() {
+    System::new().block_on(async {
+        // an actor that can never start stops its worker after a few attempts
+        let starts = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&starts);
+        let _addr = SyncArbiter::start(1, move || -> SlowActor {
+            counter.fetch_add(1, Ordering::SeqCst);
+            panic!("cannot start");
+        });
+
+        actix_rt::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(starts.load(Ordering::SeqCst), 5);
+
+        // an actor that fails to start a few times is still restarted
+        let starts = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&starts);
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let addr = SyncArbiter::start(1, move || {
+            if counter.fetch_add(1, Ordering::SeqCst) < 3 {
+                panic!("cannot start");
+            }
+            SlowActor {
+                stopped: Arc::clone(&stopped),
+            }
+        });
+
+        assert!(addr.send(Slow).await.is_ok());
+        assert_eq!(starts.load(Ordering::SeqCst), 4);
+    });
+}
+
+struct Block(std::sync::mpsc::Receiver<()>);
+
+impl Message for Block {
+    type Result = ();
+}
+
+impl Handler<Block> for SlowActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: Block, _: &mut Self::Context) {
+        let _ = msg.0.recv();
+    }
+}
+
+#[test]
+fn test_sync_queue_capacity() {
+    System::new().block_on(async {
+        let addr = SyncArbiter::builder()
+            .threads(1)
+            .queue_capacity(1)
+            .start(|| SlowActor {
+                stopped: Arc::new(AtomicUsize::new(0)),
+            });
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let blocked = addr.send(Block(rx));
+        actix_rt::time::sleep(std::time::Duration::from_millis(20)).await;
+
+        // the worker is blocked, the queue fills up
+        let accepted = (0..10).take_while(|_| addr.try_send(Slow).is_ok()).count();
+        assert!(accepted < 10);
+        assert!(matches!(addr.try_send(Slow), Err(SendError::Full(_))));
+        let waiting =
+            actix_rt::time::timeout(std::time::Duration::from_millis(50), addr.send(Slow));
+        assert!(waiting.await.is_err());
+
+        tx.send(()).unwrap();
+        blocked.await.unwrap();
+        assert!(addr.send(Slow).await.is_ok());
+    });
+}
+
+#[test]
+fn test_sync_worker_inbox_capacity() {
+    System::new().block_on(async {
+        let pool = SyncArbiter::builder()
+            .threads(1)
+            .queue_capacity(1)
+            .start_with_handle(|_| SlowActor {
+                stopped: Arc::new(AtomicUsize::new(0)),
+            });
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let blocked = pool.send_to_worker(0, Block(rx));
+        actix_rt::time::sleep(std::time::Duration::from_millis(20)).await;
+
+        // the worker is blocked, its inbox fills up
+        assert!(pool.try_send_to_worker(0, Slow).is_ok());
+        assert!(matches!(
+            pool.try_send_to_worker(0, Slow),
+            Err(SendError::Full(_))
+        ));
+        let res = pool
+            .send_to_worker(0, Slow)
+            .timeout(std::time::Duration::from_millis(50))
+            .await;
+        assert!(matches!(res, Err(MailboxError::Timeout)));
+        let waiting = pool.send_to_worker(0, Slow);
+
+        tx.send(()).unwrap();
+        blocked.await.unwrap();
+        assert!(waiting.await.is_ok());
+        assert!(matches!(
+            pool.try_send_to_worker(1, Slow),
+            Err(SendError::Closed(_))
+        ));
+    });
+}
+
+struct Identify;
+
+impl Message for Identify {
+    type Result = (usize, usize, Option<String>);
+}
+
+struct IdActor(usize);
+
+impl Actor for IdActor {
+    type Context = SyncContext<Self>;
+}
+
+impl Handler<Identify> for IdActor {
+    type Result = MessageResult<Identify>;
+
+    fn handle(&mut self, _: Identify, ctx: &mut Self::Context) -> Self::Result {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        let name = std::thread::current().name().map(str::to_owned);
+        MessageResult((self.0, ctx.worker_id(), name))
+    }
+}
+
+#[test]
+fn test_sync_pool_handle() {
+    System::new().block_on(async {
+        let pool = SyncArbiter::builder()
+            .threads(2)
+            .thread_name("identify")
+            .start_with_handle(IdActor);
+        let addr = pool.address();
+        assert_eq!(pool.workers(), 2);
+
+        let reqs: Vec<_> = (0..8).map(|_| addr.send(Identify)).collect();
+        let mut seen = std::collections::HashSet::new();
+        for req in reqs {
+            let (idx, id, name) = req.await.unwrap();
+            assert_eq!(idx, id);
+            assert_eq!(name, Some(format!("identify-{}", id)));
+            seen.insert(id);
+        }
+        assert_eq!(seen, [0, 1].iter().copied().collect());
+
+        assert_eq!(pool.add_worker(), Some(2));
+        assert_eq!(pool.workers(), 3);
+
+        // the id of a removed worker is reused
+        assert!(pool.remove_worker());
+        actix_rt::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(pool.workers(), 2);
+        let id = pool.add_worker().unwrap();
+        assert!(id < 3);
+        assert_eq!(pool.workers(), 3);
+
+        // the last worker is kept
+        assert!(pool.remove_worker());
+        assert!(pool.remove_worker());
+        assert!(!pool.remove_worker());
+        actix_rt::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(pool.workers(), 1);
+        assert!(!pool.remove_worker());
+
+        assert!(pool.shutdown(std::time::Duration::from_secs(1)).await);
+        assert_eq!(pool.add_worker(), None);
+    });
+}
+
+#[test]
+fn test_sync_remove_worker_autoscaled() {
+    System::new().block_on(async {
+        let pool = SyncArbiter::builder()
+            .autoscale(ScalingPolicy::new(2, 4))
+            .start_with_handle(IdActor);
+        assert_eq!(pool.add_worker(), Some(2));
+        assert!(pool.remove_worker());
+        assert!(!pool.remove_worker());
+        actix_rt::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(pool.workers(), 2);
+    });
+}
+
+#[derive(Clone)]
+struct Reload;
+
+impl Message for Reload {
+    type Result = usize;
+}
+
+impl Handler<Reload> for IdActor {
+    type Result = usize;
+
+    fn handle(&mut self, _: Reload, ctx: &mut Self::Context) -> usize {
+        ctx.worker_id()
+    }
+}
+
+#[test]
+fn test_sync_broadcast() {
+    System::new().block_on(async {
+        let pool = SyncArbiter::builder().threads(3).start_with_handle(IdActor);
+
+        let res = pool.broadcast(Reload).await;
+        assert_eq!(
+            res.into_iter().map(Result::unwrap).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+
+        for id in 0..3 {
+            let (_, worker, _) = pool.send_to_worker(id, Identify).await.unwrap();
+            assert_eq!(worker, id);
+        }
+        assert!(matches!(
+            pool.send_to_worker(3, Identify).await,
+            Err(MailboxError::Closed)
+        ));
+    });
+}
+
+#[derive(Clone)]
+struct Account(Option<u64>);
+
+impl Message for Account {
+    type Result = usize;
+}
+
+impl actix::actors::router::RouteKey for Account {
+    fn route_key(&self) -> Option<u64> {
+        self.0
+    }
+}
+
+impl Handler<Account> for IdActor {
+    type Result = usize;
+
+    fn handle(&mut self, _: Account, ctx: &mut Self::Context) -> usize {
+        ctx.worker_id()
+    }
+}
+
+#[test]
+fn test_sync_send_by_key() {
+    System::new().block_on(async {
+        let pool = SyncArbiter::builder().threads(4).start_with_handle(IdActor);
+
+        let mut routes = Vec::new();
+        for key in 0..32 {
+            let id = pool.send_by_key(Account(Some(key))).await.unwrap();
+            assert_eq!(pool.send_by_key(Account(Some(key))).await.unwrap(), id);
+            routes.push(id);
+        }
+        let used: std::collections::HashSet<_> = routes.iter().collect();
+        assert!(used.len() > 1);
+        assert!(pool.send_by_key(Account(None)).await.unwrap() < 4);
+
+        // only the keys of the added worker move
+        assert_eq!(pool.add_worker(), Some(4));
+        for key in 0..32 {
+            let id = pool.send_by_key(Account(Some(key))).await.unwrap();
+            assert!(id == routes[key as usize] || id == 4);
+        }
+        assert!(pool.try_send_by_key(Account(Some(1))).is_ok());
+        assert!(pool.try_send_by_key(Account(None)).is_ok());
+    });
+}
+
+struct Ticks;
+
+impl Message for Ticks {
+    type Result = Vec<&'static str>;
+}
+
+struct Retry;
+
+impl Message for Retry {
+    type Result = ();
+}
+
+struct TimerActor {
+    events: Vec<&'static str>,
+    ticks: usize,
+}
+
+impl Actor for TimerActor {
+    type Context = SyncContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(std::time::Duration::from_millis(10), |act, ctx| {
+            act.ticks += 1;
+            if act.ticks == 3 {
+                act.events.push("flushed");
+                ctx.notify(Retry);
+            }
+        });
+        let cancelled = ctx.run_later(std::time::Duration::from_millis(20), |act, _| {
+            act.events.push("cancelled");
+        });
+        assert!(ctx.cancel_timer(cancelled));
+        ctx.notify_later(Retry, std::time::Duration::from_millis(5));
+    }
+}
+
+impl Handler<Retry> for TimerActor {
+    type Result = ();
+
+    fn handle(&mut self, _: Retry, _: &mut Self::Context) {
+        self.events.push("retry");
+    }
+}
+
+impl Handler<Ticks> for TimerActor {
+    type Result = MessageResult<Ticks>;
+
+    fn handle(&mut self, _: Ticks, _: &mut Self::Context) -> Self::Result {
+        MessageResult(self.events.clone())
+    }
+}
+
+#[test]
+fn test_sync_timers() {
+    System::new().block_on(async {
+        let addr = SyncArbiter::start(1, || TimerActor {
+            events: Vec::new(),
+            ticks: 0,
+        });
+
+        actix_rt::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(
+            addr.send(Ticks).await.unwrap(),
+            ["retry", "flushed", "retry"]
+        );
+    });
+}
+
+#[test]
+fn test_sync_shutdown() {
+    System::new().block_on(async {
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&stopped);
+        let pool = SyncArbiter::builder()
+            .threads(2)
+            .start_with_handle(move |_| SlowActor {
+                stopped: Arc::clone(&counter),
+            });
+        let addr = pool.address();
+
+        let reqs: Vec<_> = (0..6).map(|_| addr.send(Slow)).collect();
+
+        // queued messages are still handled
+        assert!(pool.shutdown(std::time::Duration::from_secs(1)).await);
+        for req in reqs {
+            assert!(req.await.is_ok());
+        }
+        assert_eq!(pool.workers(), 0);
+        assert_eq!(stopped.load(Ordering::SeqCst), 2);
+        assert!(matches!(addr.try_send(Slow), Err(SendError::Closed(_))));
+    });
+}
+
+#[test]
+fn test_sync_shutdown_autoscaled() {
+    System::new().block_on(async {
+        let policy = ScalingPolicy::new(1, 4)
+            .interval(std::time::Duration::from_millis(5))
+            .queue_threshold(1);
+        let pool = SyncArbiter::builder()
+            .autoscale(policy)
+            .start_with_handle(|_| SlowActor {
+                stopped: Arc::new(AtomicUsize::new(0)),
+            });
+        let addr = pool.address();
+
+        let reqs: Vec<_> = (0..20).map(|_| addr.send(Slow)).collect();
+        actix_rt::time::sleep(std::time::Duration::from_millis(20)).await;
+
+        // the pool stops growing while it drains
+        let shutdown = pool.shutdown(std::time::Duration::from_secs(5));
+        let workers = pool.workers();
+        actix_rt::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert!(pool.workers() <= workers);
+        assert_eq!(pool.add_worker(), None);
+
+        assert!(shutdown.await);
+        for req in reqs {
+            assert!(req.await.is_ok());
+        }
+        assert_eq!(pool.workers(), 0);
+    });
+}
+
+#[test]
+fn test_sync_shutdown_timeout() {
+    System::new().block_on(async {
+        let pool = SyncArbiter::builder()
+            .threads(1)
+            .start_with_handle(|_| SlowActor {
+                stopped: Arc::new(AtomicUsize::new(0)),
+            });
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let blocked = pool.address().send(Block(rx));
+        actix_rt::time::sleep(std::time::Duration::from_millis(20)).await;
+
+        assert!(!pool.shutdown(std::time::Duration::from_millis(50)).await);
+        assert_eq!(pool.workers(), 1);
+
+        tx.send(()).unwrap();
+        blocked.await.unwrap();
+        assert!(pool.shutdown(std::time::Duration::from_secs(1)).await);
+    });
+}
+
+#[test]
+fn test_sync_shutdown_drains_workers() {
+    System::new().block_on(async {
+        for _ in 0..20 {
+            let pool = SyncArbiter::builder().threads(1).start_with_handle(IdActor);
+            let addr = pool.address();
+
+            let mut worker_reqs = Vec::new();
+            let mut reqs = Vec::new();
+            for _ in 0..10 {
+                worker_reqs.push(pool.send_to_worker(0, Reload));
+                reqs.push(addr.send(Reload));
+            }
+
+            assert!(pool.shutdown(std::time::Duration::from_secs(5)).await);
+            for req in worker_reqs {
+                assert_eq!(req.await.unwrap(), 0);
+            }
+            for req in reqs {
+                assert_eq!(req.await.unwrap(), 0);
+            }
+
+            // the pool no longer accepts messages
+            assert!(matches!(
+                pool.send_to_worker(0, Reload).await,
+                Err(MailboxError::Closed)
+            ));
+            assert!(matches!(
+                pool.send_by_key(Account(Some(1))).await,
+                Err(MailboxError::Closed)
+            ));
+            assert!(matches!(
+                pool.try_send_by_key(Account(Some(1))),
+                Err(SendError::Closed(_))
+            ));
+        }
+    });
+}